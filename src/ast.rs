@@ -1,6 +1,18 @@
+use crate::chars;
 use crate::eval::Env;
+use crate::hashtables::LispHashTable;
+use crate::parameters::LispParameter;
+use crate::ports::LispPort;
+use crate::promises::LispPromise;
+use crate::records::{LispRecord, LispRecordProc, LispRecordType};
+use crate::strings::LispString;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::mem;
 use std::rc::Rc;
 use LispErr::*;
 use LispVal::*;
@@ -13,24 +25,334 @@ pub enum LispErr {
     NotFunction(String, String),
     UnboundVar(String, String),
     NumArgs(i32, LispVal),
+    OutOfRange(String, LispVal),
+    // Opening, creating or deleting a file failed; holds the file name
+    FileError(String, LispVal),
     Default(String),
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+// How guard shows errors to Scheme code, as an error object's message and irritants
+impl LispErr {
+    pub fn message(&self) -> String {
+        match self {
+            TypeMismatch(message, _)
+            | BadSpecialForm(message, _)
+            | ParseError(message)
+            | NotFunction(_, message)
+            | UnboundVar(message, _)
+            | OutOfRange(message, _)
+            | FileError(message, _)
+            | Default(message) => message.clone(),
+            NumArgs(n, _) => format!("Expected {} arguments", n),
+        }
+    }
+
+    pub fn irritants(&self) -> Vec<LispVal> {
+        match self {
+            TypeMismatch(_, val)
+            | BadSpecialForm(_, val)
+            | NumArgs(_, val)
+            | OutOfRange(_, val)
+            | FileError(_, val) => vec![val.clone()],
+            NotFunction(name, _) | UnboundVar(_, name) => vec![Atom(name.clone())],
+            ParseError(_) | Default(_) => vec![],
+        }
+    }
+}
+
+#[derive(Clone)]
 pub enum LispVal {
     Atom(String),
+    // A symbol made by gensym, which is only ever eq? to itself, whatever its name
+    Uninterned(Rc<String>),
     ConsList(Rc<Cons>),
     Nil,
-    Number(i32),
-    Str(String),
+    Number(BigInt),
+    Str(LispString),
     Bool(bool),
+    Char(char),
+    Keyword(String),
     Func {
-        params: Vec<String>,
-        vararg: Option<String>,
+        params: Rc<Params>,
         body: Vec<LispVal>,
         env: Env,
     },
-    //PrimitiveFunc(Func)
+    PrimitiveFunc(String),
+    // The clauses of a case-lambda, each a Func
+    CaseLambda(Vec<LispVal>),
+    Vector(Rc<RefCell<Vec<LispVal>>>),
+    Bytevector(Rc<RefCell<Vec<u8>>>),
+    HashTable(Rc<RefCell<LispHashTable>>),
+    RecordType(Rc<LispRecordType>),
+    Record(Rc<LispRecord>),
+    RecordProc(Rc<LispRecordProc>),
+    Promise(Rc<LispPromise>),
+    Parameter(Rc<LispParameter>),
+    Port(Rc<LispPort>),
+    // An error caught by guard
+    ErrorObject(Rc<LispErr>),
+    // A first-class environment, which eval can evaluate expressions in
+    Environment(Env),
+    // Zero or several values returned at once by `values`
+    Values(Vec<LispVal>),
+    // The result of define, set! and other expressions evaluated only for their effects
+    Unspecified,
+    // What reading past the end of input returns
+    Eof,
+}
+
+// Values are compared structurally, like `equal?`: pairs, vectors, strings and bytevectors by their
+// contents, and anything else with `eqv?`.  Pairs and vectors can be mutated into cycles,
+// so we remember every pair of them we've started comparing: meeting one again means nothing on
+// the way round the cycle differed, so they're taken to be equal.
+fn structurally_equal(x: &LispVal, y: &LispVal, seen: &mut HashSet<(usize, usize)>) -> bool {
+    let (mut x, mut y) = (x.clone(), y.clone());
+    loop {
+        // walk along the cdrs in a loop rather than recursing, so long lists don't overflow the stack
+        let (next_x, next_y) = match (&x, &y) {
+            (ConsList(a), ConsList(b)) => {
+                if Rc::ptr_eq(a, b) || !seen.insert((node_id(&x), node_id(&y))) {
+                    return true;
+                }
+                if !structurally_equal(&a.car(), &b.car(), seen) {
+                    return false;
+                }
+                (a.cdr(), b.cdr())
+            }
+            (Vector(a), Vector(b)) => {
+                if Rc::ptr_eq(a, b) || !seen.insert((node_id(&x), node_id(&y))) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                return a.len() == b.len()
+                    && a.iter()
+                        .zip(b.iter())
+                        .all(|(x, y)| structurally_equal(x, y, seen));
+            }
+            (Str(a), Str(b)) => return a == b,
+            (Bytevector(a), Bytevector(b)) => return a == b,
+            _ => return x.is_eqv(&y),
+        };
+        x = next_x;
+        y = next_y;
+    }
+}
+
+impl PartialEq for LispVal {
+    fn eq(&self, other: &LispVal) -> bool {
+        structurally_equal(self, other, &mut HashSet::new())
+    }
+}
+
+impl Eq for LispVal {}
+
+// A variable's name.  A variable named by an uninterned symbol is distinct from every other
+// variable, including one named by an ordinary symbol with the same spelling, so code built
+// around gensyms can't capture (or be captured by) the code it's spliced into.
+#[derive(Clone, Debug)]
+pub enum Var {
+    Interned(String),
+    Uninterned(Rc<String>),
+}
+
+impl Var {
+    pub fn name(&self) -> &str {
+        match self {
+            Var::Interned(s) => s,
+            Var::Uninterned(s) => s,
+        }
+    }
+}
+
+impl PartialEq for Var {
+    fn eq(&self, other: &Var) -> bool {
+        match (self, other) {
+            (Var::Interned(x), Var::Interned(y)) => x == y,
+            (Var::Uninterned(x), Var::Uninterned(y)) => Rc::ptr_eq(x, y),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Var {}
+
+impl Hash for Var {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Var::Interned(s) => s.hash(state),
+            Var::Uninterned(s) => Rc::as_ptr(s).hash(state),
+        }
+    }
+}
+
+impl From<String> for Var {
+    fn from(name: String) -> Var {
+        Var::Interned(name)
+    }
+}
+
+impl From<&str> for Var {
+    fn from(name: &str) -> Var {
+        Var::Interned(name.to_string())
+    }
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// A procedure's parameter list, which besides required parameters and a rest parameter can have
+// DSSSL-style optional and keyword parameters: (a #!optional (b 1) #!key (c 2) #!rest r).  Their
+// defaults are expressions, evaluated when the procedure is called without them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Params {
+    pub required: Vec<Var>,
+    pub optional: Vec<(Var, LispVal)>,
+    pub keys: Vec<(Var, LispVal)>,
+    pub rest: Option<Var>,
+}
+
+impl Params {
+    // Whether a call with `n` arguments can be bound to these parameters
+    pub fn accepts(&self, n: usize) -> bool {
+        n >= self.required.len()
+            && (self.rest.is_some()
+                || !self.keys.is_empty()
+                || n <= self.required.len() + self.optional.len())
+    }
+}
+
+// Written as a lambda list, dotted if the only extra parameter is a rest parameter.
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self.required.iter().map(Var::to_string).collect();
+        let with_defaults = |marker: &str, params: &[(Var, LispVal)], parts: &mut Vec<String>| {
+            if !params.is_empty() {
+                parts.push(marker.to_string());
+                for (name, default) in params {
+                    parts.push(format!("({} {})", name, default));
+                }
+            }
+        };
+        with_defaults("#!optional", &self.optional, &mut parts);
+        with_defaults("#!key", &self.keys, &mut parts);
+        match &self.rest {
+            Some(rest) if parts.is_empty() => return write!(f, "{}", rest),
+            Some(rest) if self.optional.is_empty() && self.keys.is_empty() => {
+                parts.push(format!(". {}", rest))
+            }
+            Some(rest) => parts.push(format!("#!rest {}", rest)),
+            None => {}
+        }
+        write!(f, "({})", parts.join(" "))
+    }
+}
+
+// Hashing only looks at the first HASH_LIMIT pairs, vectors and other values it comes across, so
+// it terminates on cyclic structures.  Equal values are visited in the same order, so they still
+// hash the same.
+const HASH_LIMIT: usize = 256;
+
+impl Hash for LispVal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_bounded(state, &mut HASH_LIMIT.clone())
+    }
+}
+
+impl LispVal {
+    fn hash_bounded<H: Hasher>(&self, state: &mut H, budget: &mut usize) {
+        if *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+        mem::discriminant(self).hash(state);
+        match self {
+            Atom(s) | Keyword(s) | PrimitiveFunc(s) => s.hash(state),
+            Uninterned(s) => Rc::as_ptr(s).hash(state),
+            ConsList(cons) => {
+                cons.car().hash_bounded(state, budget);
+                cons.cdr().hash_bounded(state, budget);
+            }
+            Nil | Unspecified | Eof => {}
+            Number(i) => i.hash(state),
+            Str(s) => s.hash(state),
+            Bool(b) => b.hash(state),
+            Char(c) => c.hash(state),
+            Func { params, body, env } => {
+                params.hash(state);
+                for form in body {
+                    form.hash_bounded(state, budget);
+                }
+                env.hash(state);
+            }
+            CaseLambda(clauses) => {
+                for clause in clauses {
+                    clause.hash_bounded(state, budget);
+                }
+            }
+            Vector(vec) => {
+                for v in vec.borrow().iter() {
+                    v.hash_bounded(state, budget);
+                }
+            }
+            Bytevector(bytes) => bytes.borrow().hash(state),
+            HashTable(table) => Rc::as_ptr(table).hash(state),
+            RecordType(record_type) => Rc::as_ptr(record_type).hash(state),
+            Record(record) => Rc::as_ptr(record).hash(state),
+            RecordProc(proc) => Rc::as_ptr(proc).hash(state),
+            Promise(promise) => Rc::as_ptr(promise).hash(state),
+            Parameter(param) => Rc::as_ptr(param).hash(state),
+            Port(port) => Rc::as_ptr(port).hash(state),
+            ErrorObject(err) => Rc::as_ptr(err).hash(state),
+            Environment(env) => env.hash(state),
+            Values(vals) => {
+                for v in vals {
+                    v.hash_bounded(state, budget);
+                }
+            }
+        }
+    }
+}
+
+// Lists and vectors are shown in their written form, which terminates on cycles.
+impl fmt::Debug for LispVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Atom(s) => f.debug_tuple("Atom").field(s).finish(),
+            Uninterned(s) => f.debug_tuple("Uninterned").field(s).finish(),
+            ConsList(_) => write!(f, "ConsList({})", self),
+            Nil => write!(f, "Nil"),
+            Unspecified => write!(f, "Unspecified"),
+            Eof => write!(f, "Eof"),
+            Number(i) => f.debug_tuple("Number").field(i).finish(),
+            Str(s) => f.debug_tuple("Str").field(s).finish(),
+            Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            Char(c) => f.debug_tuple("Char").field(c).finish(),
+            Keyword(s) => f.debug_tuple("Keyword").field(s).finish(),
+            Func { params, body, env } => f
+                .debug_struct("Func")
+                .field("params", params)
+                .field("body", body)
+                .field("env", env)
+                .finish(),
+            PrimitiveFunc(name) => f.debug_tuple("PrimitiveFunc").field(name).finish(),
+            CaseLambda(clauses) => f.debug_tuple("CaseLambda").field(clauses).finish(),
+            Vector(_) => write!(f, "Vector({})", self),
+            Bytevector(bytes) => f.debug_tuple("Bytevector").field(&bytes.borrow()).finish(),
+            HashTable(table) => f.debug_tuple("HashTable").field(&table.borrow()).finish(),
+            RecordType(record_type) => f.debug_tuple("RecordType").field(record_type).finish(),
+            Record(_) => write!(f, "Record({})", self),
+            RecordProc(proc) => f.debug_tuple("RecordProc").field(proc).finish(),
+            Promise(promise) => f.debug_tuple("Promise").field(promise).finish(),
+            Parameter(param) => f.debug_tuple("Parameter").field(param).finish(),
+            Port(port) => f.debug_tuple("Port").field(port).finish(),
+            ErrorObject(err) => f.debug_tuple("ErrorObject").field(err).finish(),
+            Environment(env) => env.fmt(f),
+            Values(vals) => f.debug_tuple("Values").field(vals).finish(),
+        }
+    }
 }
 
 impl LispVal {
@@ -43,7 +365,7 @@ impl LispVal {
 
     pub fn car(&self) -> Result<LispVal, LispErr> {
         match self {
-            ConsList(c) => Ok(c.car.as_ref().clone()),
+            ConsList(c) => Ok(c.car()),
             _ => Err(TypeMismatch(
                 "Expected an cons cell".to_string(),
                 self.clone(),
@@ -53,7 +375,7 @@ impl LispVal {
 
     pub fn cdr(&self) -> Result<LispVal, LispErr> {
         match self {
-            ConsList(c) => Ok(c.cdr.as_ref().clone()),
+            ConsList(c) => Ok(c.cdr()),
             _ => Err(TypeMismatch(
                 "Expected a cons cell".to_string(),
                 self.clone(),
@@ -63,13 +385,123 @@ impl LispVal {
 
     pub fn cons(car: LispVal, cdr: LispVal) -> LispVal {
         ConsList(Rc::new(Cons {
-            car: Rc::new(car),
-            cdr: Rc::new(cdr),
+            car: RefCell::new(car),
+            cdr: RefCell::new(cdr),
         }))
     }
 
+    // `eq?`: whether two values are the same object.  Pairs, strings, vectors and other mutable
+    // objects are compared by address, and procedures by the environment and code they were created
+    // from.  Symbols, booleans and characters are immediate values, as are numbers that fit in a
+    // machine word; bignums are only ever eqv?.
+    pub fn is_eq(&self, other: &LispVal) -> bool {
+        match (self, other) {
+            (Atom(x), Atom(y))
+            | (Keyword(x), Keyword(y))
+            | (PrimitiveFunc(x), PrimitiveFunc(y)) => x == y,
+            (Uninterned(x), Uninterned(y)) => Rc::ptr_eq(x, y),
+            (Nil, Nil) | (Unspecified, Unspecified) | (Eof, Eof) => true,
+            (Bool(x), Bool(y)) => x == y,
+            (Char(x), Char(y)) => x == y,
+            (Number(x), Number(y)) => x.to_i64().is_some() && x == y,
+            (Str(x), Str(y)) => x.ptr_eq(y),
+            (ConsList(x), ConsList(y)) => Rc::ptr_eq(x, y),
+            (Vector(x), Vector(y)) => Rc::ptr_eq(x, y),
+            (Bytevector(x), Bytevector(y)) => Rc::ptr_eq(x, y),
+            (HashTable(x), HashTable(y)) => Rc::ptr_eq(x, y),
+            (RecordType(x), RecordType(y)) => Rc::ptr_eq(x, y),
+            (Record(x), Record(y)) => Rc::ptr_eq(x, y),
+            (RecordProc(x), RecordProc(y)) => Rc::ptr_eq(x, y),
+            (Promise(x), Promise(y)) => Rc::ptr_eq(x, y),
+            (Parameter(x), Parameter(y)) => Rc::ptr_eq(x, y),
+            (Port(x), Port(y)) => Rc::ptr_eq(x, y),
+            (ErrorObject(x), ErrorObject(y)) => Rc::ptr_eq(x, y),
+            (Environment(x), Environment(y)) => x == y,
+            (Values(x), Values(y)) => x == y,
+            (
+                Func { params, body, env },
+                Func {
+                    params: params2,
+                    body: body2,
+                    env: env2,
+                },
+            ) => {
+                env == env2
+                    && params == params2
+                    && body.len() == body2.len()
+                    && body.iter().zip(body2).all(|(x, y)| x.is_eq(y))
+            }
+            (CaseLambda(x), CaseLambda(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(x, y)| x.is_eq(y))
+            }
+            _ => false,
+        }
+    }
+
+    // `eqv?` is `eq?`, except that all numbers are compared by value.
+    pub fn is_eqv(&self, other: &LispVal) -> bool {
+        match (self, other) {
+            (Number(x), Number(y)) => x == y,
+            _ => self.is_eq(other),
+        }
+    }
+
+    pub fn set_car(&self, val: &LispVal) -> Result<LispVal, LispErr> {
+        match self {
+            ConsList(c) => {
+                c.car.replace(val.clone());
+                Ok(Unspecified)
+            }
+            _ => Err(TypeMismatch(
+                "Expected a cons cell".to_string(),
+                self.clone(),
+            )),
+        }
+    }
+
+    pub fn set_cdr(&self, val: &LispVal) -> Result<LispVal, LispErr> {
+        match self {
+            ConsList(c) => {
+                c.cdr.replace(val.clone());
+                Ok(Unspecified)
+            }
+            _ => Err(TypeMismatch(
+                "Expected a cons cell".to_string(),
+                self.clone(),
+            )),
+        }
+    }
+
+    pub fn list(vals: &[LispVal]) -> LispVal {
+        vals.iter()
+            .rev()
+            .fold(Nil, |list, v| LispVal::cons(v.clone(), list))
+    }
+
     pub fn iter(&self) -> LispIter {
-        LispIter { val: self }
+        let (len, tail) = list_shape(self);
+        LispIter {
+            val: self.clone(),
+            remaining: match tail {
+                Some(_) => None,
+                None => Some(len),
+            },
+        }
+    }
+
+    // The elements of a proper list, failing on improper and circular lists
+    pub fn list_items(&self) -> Result<Vec<LispVal>, LispErr> {
+        match list_shape(self) {
+            (_, Some(Nil)) => Ok(self.iter().collect()),
+            (_, Some(_)) => Err(TypeMismatch(
+                "Expected a proper list".to_string(),
+                self.clone(),
+            )),
+            (_, None) => Err(TypeMismatch(
+                "Expected a finite list".to_string(),
+                self.clone(),
+            )),
+        }
     }
 
     pub fn boolean(&self) -> Result<bool, LispErr> {
@@ -82,9 +514,79 @@ impl LispVal {
         }
     }
 
-    pub fn integer(&self) -> Result<i32, LispErr> {
+    pub fn character(&self) -> Result<char, LispErr> {
         match self {
-            Number(i) => Ok(*i),
+            Char(c) => Ok(*c),
+            _ => Err(TypeMismatch(
+                "Expected a character".to_string(),
+                self.clone(),
+            )),
+        }
+    }
+
+    pub fn string(&self) -> Result<LispString, LispErr> {
+        match self {
+            Str(s) => Ok(s.clone()),
+            _ => Err(TypeMismatch("Expected a string".to_string(), self.clone())),
+        }
+    }
+
+    pub fn symbol(&self) -> Result<String, LispErr> {
+        match self {
+            Atom(s) => Ok(s.clone()),
+            Uninterned(s) => Ok(s.to_string()),
+            _ => Err(TypeMismatch("Expected a symbol".to_string(), self.clone())),
+        }
+    }
+
+    // The variable a symbol names
+    pub fn var(&self) -> Result<Var, LispErr> {
+        match self {
+            Atom(s) => Ok(Var::Interned(s.clone())),
+            Uninterned(s) => Ok(Var::Uninterned(s.clone())),
+            _ => Err(TypeMismatch("Expected a symbol".to_string(), self.clone())),
+        }
+    }
+
+    pub fn index(&self) -> Result<usize, LispErr> {
+        match self {
+            Number(i) => i.to_usize().ok_or_else(|| {
+                OutOfRange("Expected a non-negative index".to_string(), self.clone())
+            }),
+            _ => Err(TypeMismatch("Expected an index".to_string(), self.clone())),
+        }
+    }
+
+    pub fn vector(&self) -> Result<Rc<RefCell<Vec<LispVal>>>, LispErr> {
+        match self {
+            Vector(vec) => Ok(vec.clone()),
+            _ => Err(TypeMismatch("Expected a vector".to_string(), self.clone())),
+        }
+    }
+
+    pub fn bytevector(&self) -> Result<Rc<RefCell<Vec<u8>>>, LispErr> {
+        match self {
+            Bytevector(bytes) => Ok(bytes.clone()),
+            _ => Err(TypeMismatch(
+                "Expected a bytevector".to_string(),
+                self.clone(),
+            )),
+        }
+    }
+
+    pub fn hash_table(&self) -> Result<Rc<RefCell<LispHashTable>>, LispErr> {
+        match self {
+            HashTable(table) => Ok(table.clone()),
+            _ => Err(TypeMismatch(
+                "Expected a hash table".to_string(),
+                self.clone(),
+            )),
+        }
+    }
+
+    pub fn integer(&self) -> Result<BigInt, LispErr> {
+        match self {
+            Number(i) => Ok(i.clone()),
             _ => Err(TypeMismatch(
                 "Expected an integer".to_string(),
                 self.clone(),
@@ -93,40 +595,228 @@ impl LispVal {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+// Pairs are mutable, and shared rather than copied: a pair is cloned by cloning the Rc around it,
+// so set-car! and set-cdr! are seen through every reference to it.
 pub struct Cons {
-    pub car: Rc<LispVal>,
-    pub cdr: Rc<LispVal>,
+    pub car: RefCell<LispVal>,
+    pub cdr: RefCell<LispVal>,
 }
 
 impl Cons {
+    pub fn car(&self) -> LispVal {
+        self.car.borrow().clone()
+    }
+
+    pub fn cdr(&self) -> LispVal {
+        self.cdr.borrow().clone()
+    }
+
     pub fn is_dotted(&self) -> bool {
-        match self.cdr.as_ref() {
-            ConsList(cons) => cons.is_dotted(),
-            Nil => false,
-            _ => true,
+        match list_shape(&self.cdr()) {
+            (_, Some(Nil)) => false,
+            (_, Some(_)) => true,
+            (_, None) => false,
+        }
+    }
+}
+
+fn is_same_pair(x: &LispVal, y: &LispVal) -> bool {
+    match (x, y) {
+        (ConsList(x), ConsList(y)) => Rc::ptr_eq(x, y),
+        _ => false,
+    }
+}
+
+// Follows the cdrs from `list`, returning the number of distinct pairs along the way and the
+// value that ends the list, or None if the cdrs lead round in a circle.  This is Floyd's cycle
+// finding algorithm, so it takes no extra space.
+pub fn list_shape(list: &LispVal) -> (usize, Option<LispVal>) {
+    fn next(val: &LispVal) -> LispVal {
+        match val {
+            ConsList(cons) => cons.cdr(),
+            _ => Nil,
+        }
+    }
+
+    let mut hare = list.clone();
+    let mut tortoise = list.clone();
+    let mut len = 0;
+    loop {
+        if !hare.is_cons() {
+            return (len, Some(hare));
+        }
+        hare = next(&hare);
+        len += 1;
+        if len % 2 == 0 {
+            tortoise = next(&tortoise);
+            if is_same_pair(&hare, &tortoise) {
+                break;
+            }
+        }
+    }
+    // The tortoise is now a whole number of laps round the cycle ahead of the start, so walking
+    // from both at the same speed meets at the first pair in the cycle.
+    let mut start = list.clone();
+    let mut first_in_cycle = 0;
+    while !is_same_pair(&start, &tortoise) {
+        start = next(&start);
+        tortoise = next(&tortoise);
+        first_in_cycle += 1;
+    }
+    let mut cycle_len = 1;
+    let mut pair = next(&start);
+    while !is_same_pair(&pair, &start) {
+        pair = next(&pair);
+        cycle_len += 1;
+    }
+    (first_in_cycle + cycle_len, None)
+}
+
+// Pairs, vectors and records are the values that can be part of a cycle; they're identified by
+// address.
+fn node_id(val: &LispVal) -> usize {
+    match val {
+        ConsList(cons) => Rc::as_ptr(cons) as *const () as usize,
+        Vector(vec) => Rc::as_ptr(vec) as *const () as usize,
+        Record(record) => Rc::as_ptr(record) as *const () as usize,
+        _ => 0,
+    }
+}
+
+// Finds the pairs and vectors reachable from `val` that lead back round to themselves.  `path`
+// holds those we're in the middle of exploring, so meeting one of them again means we've found a
+// cycle.
+fn find_cycles(
+    val: &LispVal,
+    path: &mut HashSet<usize>,
+    done: &mut HashSet<usize>,
+    cyclic: &mut HashSet<usize>,
+) {
+    let mut val = val.clone();
+    let mut walked = Vec::new();
+    loop {
+        let id = node_id(&val);
+        if id == 0 || done.contains(&id) {
+            break;
+        }
+        if path.contains(&id) {
+            cyclic.insert(id);
+            break;
         }
+        path.insert(id);
+        walked.push(id);
+        val = match &val {
+            ConsList(cons) => {
+                find_cycles(&cons.car(), path, done, cyclic);
+                cons.cdr()
+            }
+            Vector(vec) => {
+                for v in vec.borrow().iter() {
+                    find_cycles(v, path, done, cyclic);
+                }
+                break;
+            }
+            Record(record) => {
+                for v in record.fields.borrow().iter() {
+                    find_cycles(v, path, done, cyclic);
+                }
+                break;
+            }
+            _ => break,
+        };
     }
+    for id in walked {
+        path.remove(&id);
+        done.insert(id);
+    }
+}
+
+// Prints a value, labelling the pairs and vectors that are part of a cycle the way R7RS's `write`
+// does, so that e.g. a circular list prints as #0=(1 2 . #0#).
+struct Printer {
+    written: bool,
+    cyclic: HashSet<usize>,
+    labels: HashMap<usize, usize>,
+}
 
-    fn fmt_cons(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        (&*self.car).fmt(f)?;
+impl Printer {
+    fn new(val: &LispVal, written: bool) -> Printer {
+        let mut cyclic = HashSet::new();
+        find_cycles(val, &mut HashSet::new(), &mut HashSet::new(), &mut cyclic);
+        Printer {
+            written,
+            cyclic,
+            labels: HashMap::new(),
+        }
+    }
 
-        match &self.cdr.as_ref() {
-            Nil => write!(f, ""),
+    fn print(&mut self, val: &LispVal, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = node_id(val);
+        if self.cyclic.contains(&id) {
+            if let Some(label) = self.labels.get(&id) {
+                return write!(f, "#{}#", label);
+            }
+            let label = self.labels.len();
+            self.labels.insert(id, label);
+            write!(f, "#{}=", label)?;
+        }
+        match val {
             ConsList(cons) => {
-                write!(f, " ")?;
-                (&*cons).fmt_cons(f)
+                write!(f, "(")?;
+                self.print_cons(cons.clone(), f)?;
+                write!(f, ")")
+            }
+            Vector(vec) => {
+                write!(f, "#(")?;
+                for (i, v) in vec.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    self.print(v, f)?;
+                }
+                write!(f, ")")
             }
-            _ => {
-                // Dotted list
-                write!(f, " . ")?;
-                self.cdr.fmt(f)
+            Record(record) => {
+                write!(f, "#<{}", record.record_type.name)?;
+                let fields = record.fields.borrow();
+                for (name, v) in record.record_type.fields.iter().zip(fields.iter()) {
+                    write!(f, " {}: ", name)?;
+                    self.print(v, f)?;
+                }
+                write!(f, ">")
             }
+            _ => val.fmt_val(f, self.written),
+        }
+    }
+
+    fn print_cons(&mut self, mut cons: Rc<Cons>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        loop {
+            self.print(&cons.car(), f)?;
+            let cdr = cons.cdr();
+            cons = match &cdr {
+                Nil => return Ok(()),
+                ConsList(next) if !self.cyclic.contains(&node_id(&cdr)) => {
+                    write!(f, " ")?;
+                    next.clone()
+                }
+                // Dotted list, or the rest of the list is labelled
+                cdr => {
+                    write!(f, " . ")?;
+                    return self.print(cdr, f);
+                }
+            };
         }
     }
 }
-impl fmt::Display for LispVal {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+impl LispVal {
+    // Display formats values the way `write` does, as literals that read back in as the same
+    // value.  `display` instead prints strings and characters as their raw contents.
+    pub fn display(&self) -> Displayed<'_> {
+        Displayed(self)
+    }
+
+    fn fmt_val(&self, f: &mut fmt::Formatter<'_>, written: bool) -> fmt::Result {
         fn spaced<T: fmt::Display>(vec: &Vec<T>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             // slightly hacky way to intercalate a space between every argument
             let mut is_first = true;
@@ -141,54 +831,127 @@ impl fmt::Display for LispVal {
             Ok(())
         }
         match self {
-            Func {
-                params,
-                vararg,
-                body,
-                env,
-            } => {
-                write!(f, "(lambda (")?;
-                spaced(params, f)?;
-                for v in vararg.iter() {
-                    //
-                    write!(f, " . {}", v)?;
-                }
-                write!(f, ") ")?;
+            Func { params, body, .. } => {
+                write!(f, "(lambda {} ", params)?;
                 spaced(body, f)?;
                 write!(f, ")")
             }
+            PrimitiveFunc(name) => write!(f, "#<primitive {}>", name),
+            CaseLambda(clauses) => {
+                write!(f, "(case-lambda")?;
+                for clause in clauses {
+                    if let Func { params, body, .. } = clause {
+                        write!(f, " ({} ", params)?;
+                        spaced(body, f)?;
+                        write!(f, ")")?;
+                    }
+                }
+                write!(f, ")")
+            }
+            ConsList(_) | Vector(_) | Record(_) => Printer::new(self, written).print(self, f),
+            Bytevector(bytes) => {
+                write!(f, "#u8(")?;
+                for (i, b) in bytes.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", b)?;
+                }
+                write!(f, ")")
+            }
+            HashTable(table) => write!(f, "#<hash-table {}>", table.borrow().size()),
+            RecordType(record_type) => write!(f, "#<record-type {}>", record_type.name),
+            RecordProc(proc) => write!(f, "#<procedure {}>", proc.name),
+            Promise(_) => write!(f, "#<promise>"),
+            Parameter(_) => write!(f, "#<parameter>"),
+            Port(port) => write!(f, "#<port {}>", port.name),
+            ErrorObject(err) => write!(f, "#<error {}>", err.message()),
+            Environment(_) => write!(f, "#<environment>"),
+            Values(vals) => {
+                for (i, v) in vals.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    v.fmt_val(f, written)?;
+                }
+                Ok(())
+            }
             Atom(s) => write!(f, "{}", s),
-            Str(s) => write!(f, "\"{}\"", s),
+            Uninterned(s) => write!(f, "{}", s),
+            Keyword(s) => write!(f, "#:{}", s),
+            Str(s) if written => write_string(s, f),
+            Str(s) => write!(f, "{}", s),
+            Char(c) if !written => write!(f, "{}", c),
+            Char(c) => match chars::char_name(*c) {
+                Some(name) => write!(f, "#\\{}", name),
+                None if c.is_control() => write!(f, "#\\x{:x}", *c as u32),
+                None => write!(f, "#\\{}", c),
+            },
             Number(i) => write!(f, "{}", i),
             Bool(true) => write!(f, "#t"),
             Bool(false) => write!(f, "#f"),
             Nil => write!(f, "()"),
-            ConsList(cons) => {
-                write!(f, "(")?;
-                (&**cons).fmt_cons(f)?;
-                write!(f, ")")
-            }
+            Unspecified => write!(f, "#<unspecified>"),
+            Eof => write!(f, "#<eof>"),
+        }
+    }
+}
+
+// Writes a string literal, escaping anything the reader wouldn't read back as itself.
+fn write_string(s: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\u{7}' => write!(f, "\\a")?,
+            '\u{8}' => write!(f, "\\b")?,
+            '\t' => write!(f, "\\t")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            c if c.is_control() => write!(f, "\\x{:x};", c as u32)?,
+            c => write!(f, "{}", c)?,
         }
     }
+    write!(f, "\"")
 }
 
-pub struct LispIter<'a> {
-    val: &'a LispVal,
+impl fmt::Display for LispVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_val(f, true)
+    }
 }
 
-impl<'a> Iterator for LispIter<'a> {
-    type Item = &'a LispVal;
+pub struct Displayed<'a>(&'a LispVal);
 
-    fn next(&mut self) -> Option<&'a LispVal> {
-        let val = self.val;
-        self.val = match self.val {
-            ConsList(cons) => &(*cons).cdr,
-            _ => &Nil,
-        };
-        match val {
-            ConsList(cons) => Some(&*(*cons).car),
-            Nil => None,
-            _ => Some(val),
+impl<'a> fmt::Display for Displayed<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_val(f, false)
+    }
+}
+
+// Iterates over the elements of a list, followed by the final cdr if the list is improper.  A
+// circular list yields each element once.
+pub struct LispIter {
+    val: LispVal,
+    remaining: Option<usize>,
+}
+
+impl Iterator for LispIter {
+    type Item = LispVal;
+
+    fn next(&mut self) -> Option<LispVal> {
+        match &mut self.remaining {
+            Some(0) => return None,
+            Some(n) => *n -= 1,
+            None => {}
         }
+        let (item, rest) = match &self.val {
+            ConsList(cons) => (Some(cons.car()), cons.cdr()),
+            Nil => (None, Nil),
+            val => (Some(val.clone()), Nil),
+        };
+        self.val = rest;
+        item
     }
 }
@@ -0,0 +1,144 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+// SRFI 151 treats integers as infinite two's complement bit strings, so a negative number has
+// infinitely many leading 1 bits.  num_bigint's bitwise operators already follow that convention;
+// the helpers here cover the operations that need to look at individual bits.
+
+fn bit_index(val: &LispVal) -> Result<usize, LispErr> {
+    val.integer()?
+        .to_usize()
+        .ok_or_else(|| TypeMismatch("Expected a non-negative bit index".to_string(), val.clone()))
+}
+
+// 2^index, built on a digit vector that's reserved fallibly, so a bit too far out to fit in
+// memory is an error rather than an abort.  Callers combine it with smaller numbers by using it as
+// the left operand, which num_bigint updates in place.
+fn single_bit(index: usize, val: &LispVal) -> Result<BigInt, LispErr> {
+    let mut digits: Vec<u32> = Vec::new();
+    digits
+        .try_reserve_exact(index / 32 + 1)
+        .map_err(|_| OutOfRange("Bit index is too large".to_string(), val.clone()))?;
+    digits.resize(index / 32, 0);
+    digits.push(1 << (index % 32));
+    Ok(BigInt::from_biguint(Sign::Plus, BigUint::new(digits)))
+}
+
+// For a negative number, the interesting bits are the 0s, which are the 1s of its complement.
+fn magnitude_bits(i: &BigInt) -> BigInt {
+    if i.is_negative() {
+        !i
+    } else {
+        i.clone()
+    }
+}
+
+pub fn arithmetic_shift(i: &LispVal, count: &LispVal) -> Result<LispVal, LispErr> {
+    let i = i.integer()?;
+    let count = count.integer()?;
+    if count.is_negative() {
+        // shifting right by more bits than the number has leaves only the sign
+        match (-count).to_usize() {
+            Some(n) => Ok(Number(i >> n)),
+            None if i.is_negative() => Ok(Number((-1).into())),
+            None => Ok(Number(0.into())),
+        }
+    } else {
+        match count.to_usize() {
+            Some(n) => Ok(Number(i << n)),
+            None => Err(Default(format!("Shift count {} is too large", count))),
+        }
+    }
+}
+
+pub fn bit_count(i: &LispVal) -> Result<LispVal, LispErr> {
+    let (_, digits) = magnitude_bits(&i.integer()?).to_u32_digits();
+    let count: u32 = digits.iter().map(|d| d.count_ones()).sum();
+    Ok(Number(count.into()))
+}
+
+pub fn integer_length(i: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number(magnitude_bits(&i.integer()?).bits().into()))
+}
+
+pub fn bitwise_if(mask: &LispVal, i: &LispVal, j: &LispVal) -> Result<LispVal, LispErr> {
+    let mask = mask.integer()?;
+    Ok(Number((&mask & i.integer()?) | (!mask & j.integer()?)))
+}
+
+pub fn bit_set(index: &LispVal, i: &LispVal) -> Result<LispVal, LispErr> {
+    let index = bit_index(index)?;
+    Ok(Bool(((i.integer()? >> index) & BigInt::one()).is_one()))
+}
+
+pub fn copy_bit(index: &LispVal, i: &LispVal, set: &LispVal) -> Result<LispVal, LispErr> {
+    let n = bit_index(index)?;
+    let (i, set) = (i.integer()?, set.boolean()?);
+    // past its integer-length, every bit of a number is the same as its sign
+    if n >= magnitude_bits(&i).bits() && set == i.is_negative() {
+        return Ok(Number(i));
+    }
+    let bit = single_bit(n, index)?;
+    if set {
+        Ok(Number(bit | i))
+    } else {
+        Ok(Number(!bit & i))
+    }
+}
+
+pub fn bit_swap(index1: &LispVal, index2: &LispVal, i: &LispVal) -> Result<LispVal, LispErr> {
+    let (n1, n2) = (bit_index(index1)?, bit_index(index2)?);
+    let i = i.integer()?;
+    let first = (&i >> n1) & BigInt::one();
+    let second = (&i >> n2) & BigInt::one();
+    if first == second {
+        Ok(Number(i))
+    } else {
+        // the bits differ, so swapping them is the same as flipping both
+        Ok(Number(
+            single_bit(n1, index1)? ^ single_bit(n2, index2)? ^ i,
+        ))
+    }
+}
+
+pub fn first_set_bit(i: &LispVal) -> Result<LispVal, LispErr> {
+    let i = i.integer()?;
+    if i.is_zero() {
+        return Ok(Number((-1).into()));
+    }
+    // two's complement negation preserves the trailing zeros, so the magnitude is enough
+    let (_, digits) = i.abs().to_u32_digits();
+    let mut zeros = 0;
+    for d in digits {
+        if d == 0 {
+            zeros += 32;
+        } else {
+            zeros += d.trailing_zeros();
+            break;
+        }
+    }
+    Ok(Number(zeros.into()))
+}
+
+pub fn bit_field(i: &LispVal, start: &LispVal, end_val: &LispVal) -> Result<LispVal, LispErr> {
+    let (start, end) = (bit_index(start)?, bit_index(end_val)?);
+    if end < start {
+        return Err(Default(format!(
+            "bit-field end {} is before start {}",
+            end, start
+        )));
+    }
+    let i = i.integer()?;
+    // a non-negative number has no bits set past its integer-length, so the field can stop there
+    let (start, end) = if i.is_negative() {
+        (start, end)
+    } else {
+        let len = i.bits();
+        (start.min(len), end.min(len))
+    };
+    let mask = single_bit(end - start, end_val)? - BigInt::one();
+    Ok(Number(mask & (i >> start)))
+}
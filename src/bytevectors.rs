@@ -0,0 +1,219 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::strings;
+use crate::vectors;
+use num_bigint::{BigInt, Sign};
+use num_traits::{Signed, ToPrimitive};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub fn new_bytevector(bytes: Vec<u8>) -> LispVal {
+    Bytevector(Rc::new(RefCell::new(bytes)))
+}
+
+pub fn byte(val: &LispVal) -> Result<u8, LispErr> {
+    val.integer()?
+        .to_u8()
+        .ok_or_else(|| TypeMismatch("Expected a byte".to_string(), val.clone()))
+}
+
+// Checks that `size` bytes starting at `k` are within the bytevector, returning the start index.
+fn check_index(bytes: &[u8], k: &LispVal, size: usize) -> Result<usize, LispErr> {
+    let i = k.index()?;
+    if i.checked_add(size)
+        .filter(|end| *end <= bytes.len())
+        .is_some()
+    {
+        Ok(i)
+    } else {
+        Err(OutOfRange(
+            format!(
+                "Bytevector index is out of range for a bytevector of length {}",
+                bytes.len()
+            ),
+            k.clone(),
+        ))
+    }
+}
+
+pub fn make_bytevector(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [k] => Ok(new_bytevector(vectors::filled("Bytevector", k, 0)?)),
+        [k, fill] => Ok(new_bytevector(vectors::filled(
+            "Bytevector",
+            k,
+            byte(fill)?,
+        )?)),
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+pub fn bytevector(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let bytes = args
+        .iter()
+        .map(byte)
+        .collect::<Result<Vec<u8>, LispErr>>()?;
+    Ok(new_bytevector(bytes))
+}
+
+pub fn bytevector_length(bv: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number(bv.bytevector()?.borrow().len().into()))
+}
+
+pub fn bytevector_u8_ref(bv: &LispVal, k: &LispVal) -> Result<LispVal, LispErr> {
+    let bytes = bv.bytevector()?;
+    let bytes = bytes.borrow();
+    Ok(Number(bytes[check_index(&bytes, k, 1)?].into()))
+}
+
+pub fn bytevector_u8_set(bv: &LispVal, k: &LispVal, b: &LispVal) -> Result<LispVal, LispErr> {
+    let bytes = bv.bytevector()?;
+    let mut bytes = bytes.borrow_mut();
+    let i = check_index(&bytes, k, 1)?;
+    bytes[i] = byte(b)?;
+    Ok(Unspecified)
+}
+
+pub fn bytevector_copy(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [bv, rest @ ..] if rest.len() <= 2 => {
+            let bytes = bv.bytevector()?;
+            let bytes = bytes.borrow();
+            let (from, to) = vectors::range("Bytevector", bytes.len(), rest.first(), rest.get(1))?;
+            Ok(new_bytevector(bytes[from..to].to_vec()))
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// (bytevector-copy! to at from [start [end]])
+pub fn bytevector_copy_into(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [to, at, from, rest @ ..] if rest.len() <= 2 => {
+            // copy the source first, since it may be the destination too
+            let source = from.bytevector()?.borrow().clone();
+            let (start, end) =
+                vectors::range("Bytevector", source.len(), rest.first(), rest.get(1))?;
+            let dest = to.bytevector()?;
+            let mut dest = dest.borrow_mut();
+            let at_index = check_index(&dest, at, end - start)?;
+            dest[at_index..at_index + end - start].copy_from_slice(&source[start..end]);
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(5, LispVal::list(args))),
+    }
+}
+
+pub fn bytevector_append(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let mut bytes = Vec::new();
+    for bv in args {
+        bytes.extend(bv.bytevector()?.borrow().iter());
+    }
+    Ok(new_bytevector(bytes))
+}
+
+pub fn utf8_to_string(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [bv, rest @ ..] if rest.len() <= 2 => {
+            let bytes = bv.bytevector()?;
+            let bytes = bytes.borrow();
+            let (from, to) = vectors::range("Bytevector", bytes.len(), rest.first(), rest.get(1))?;
+            match std::str::from_utf8(&bytes[from..to]) {
+                Ok(s) => Ok(Str(s.into())),
+                Err(_) => Err(TypeMismatch(
+                    "Expected a bytevector containing valid UTF-8".to_string(),
+                    bv.clone(),
+                )),
+            }
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+pub fn string_to_utf8(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [s, rest @ ..] if rest.len() <= 2 => {
+            let s = s.string()?;
+            let selected = strings::slice(&s, rest.first(), rest.get(1))?;
+            Ok(new_bytevector(selected.as_bytes().to_vec()))
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// R6RS multi-byte accessors take an endianness symbol, `big` or `little`.
+fn is_big_endian(endianness: &LispVal) -> Result<bool, LispErr> {
+    match endianness {
+        Atom(e) if e == "big" => Ok(true),
+        Atom(e) if e == "little" => Ok(false),
+        _ => Err(TypeMismatch(
+            "Expected an endianness, 'big or 'little".to_string(),
+            endianness.clone(),
+        )),
+    }
+}
+
+pub fn native_endianness(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [] if cfg!(target_endian = "big") => Ok(Atom("big".to_string())),
+        [] => Ok(Atom("little".to_string())),
+        _ => Err(NumArgs(0, LispVal::list(args))),
+    }
+}
+
+// (bytevector-u32-ref bv k endianness) and friends, reading a `size` byte integer at index k
+pub fn int_ref(size: usize, signed: bool, args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [bv, k, endianness] => {
+            let bytes = bv.bytevector()?;
+            let bytes = bytes.borrow();
+            let i = check_index(&bytes, k, size)?;
+            let mut int_bytes = bytes[i..i + size].to_vec();
+            if is_big_endian(endianness)? {
+                int_bytes.reverse();
+            }
+            if signed {
+                Ok(Number(BigInt::from_signed_bytes_le(&int_bytes)))
+            } else {
+                Ok(Number(BigInt::from_bytes_le(Sign::Plus, &int_bytes)))
+            }
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// (bytevector-u32-set! bv k n endianness) and friends, writing a `size` byte integer at index k
+pub fn int_set(size: usize, signed: bool, args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [bv, k, n, endianness] => {
+            let int = n.integer()?;
+            let bits = 8 * size;
+            let fits = if signed {
+                int.bits() < bits || (int.is_negative() && (!&int).bits() < bits)
+            } else {
+                !int.is_negative() && int.bits() <= bits
+            };
+            if !fits {
+                return Err(OutOfRange(
+                    format!("Integer doesn't fit in {} bytes", size),
+                    n.clone(),
+                ));
+            }
+            // two's complement, sign extended out to the full size
+            let mut int_bytes = int.to_signed_bytes_le();
+            let fill = if int.is_negative() { 0xff } else { 0 };
+            int_bytes.resize(size, fill);
+            if is_big_endian(endianness)? {
+                int_bytes.reverse();
+            }
+
+            let bytes = bv.bytevector()?;
+            let mut bytes = bytes.borrow_mut();
+            let i = check_index(&bytes, k, size)?;
+            bytes[i..i + size].copy_from_slice(&int_bytes);
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(4, LispVal::list(args))),
+    }
+}
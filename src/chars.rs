@@ -0,0 +1,84 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use num_traits::ToPrimitive;
+
+// Character names accepted by the reader after `#\`, and used by the printer for characters
+// that would otherwise be invisible.
+pub const CHAR_NAMES: &[(&str, char)] = &[
+    ("alarm", '\u{7}'),
+    ("backspace", '\u{8}'),
+    ("delete", '\u{7f}'),
+    ("escape", '\u{1b}'),
+    ("newline", '\n'),
+    ("null", '\0'),
+    ("return", '\r'),
+    ("space", ' '),
+    ("tab", '\t'),
+];
+
+// The zero of every run of Unicode decimal digits (general category Nd).  Each run is exactly ten
+// consecutive code points, 0 through 9, so the zeros are enough to recover any digit's value.
+const DIGIT_ZEROS: &[u32] = &[
+    0x30, 0x660, 0x6F0, 0x7C0, 0x966, 0x9E6, 0xA66, 0xAE6, 0xB66, 0xBE6, 0xC66, 0xCE6, 0xD66,
+    0xDE6, 0xE50, 0xED0, 0xF20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80, 0x1A90,
+    0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0, 0xFF10,
+    0x104A0, 0x10D30, 0x11066, 0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450, 0x114D0, 0x11650,
+    0x116C0, 0x11730, 0x118E0, 0x11950, 0x11C50, 0x11D50, 0x11DA0, 0x16A60, 0x16AC0, 0x16B50,
+    0x1D7CE, 0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0, 0x1E950, 0x1FBF0,
+];
+
+pub fn char_from_name(name: &str) -> Option<char> {
+    CHAR_NAMES.iter().find(|(n, _)| *n == name).map(|(_, c)| *c)
+}
+
+pub fn char_name(c: char) -> Option<&'static str> {
+    CHAR_NAMES.iter().find(|(_, ch)| *ch == c).map(|(n, _)| *n)
+}
+
+pub fn digit_value(c: char) -> Option<u32> {
+    let code = c as u32;
+    DIGIT_ZEROS
+        .iter()
+        .find(|zero| **zero <= code && code < **zero + 10)
+        .map(|zero| code - zero)
+}
+
+// Rust's case mappings can expand a character into several (e.g. 'ß' upcases to "SS").  A
+// character can't hold that, so like most Schemes we leave such characters unchanged.
+fn single<I: Iterator<Item = char>>(c: char, mut mapped: I) -> char {
+    match (mapped.next(), mapped.next()) {
+        (Some(m), None) => m,
+        _ => c,
+    }
+}
+
+pub fn upcase(c: char) -> char {
+    single(c, c.to_uppercase())
+}
+
+pub fn downcase(c: char) -> char {
+    single(c, c.to_lowercase())
+}
+
+pub fn foldcase(c: char) -> char {
+    downcase(upcase(c))
+}
+
+pub fn char_to_integer(c: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number((c.character()? as u32).into()))
+}
+
+pub fn integer_to_char(i: &LispVal) -> Result<LispVal, LispErr> {
+    i.integer()?
+        .to_u32()
+        .and_then(std::char::from_u32)
+        .map(Char)
+        .ok_or_else(|| TypeMismatch("Expected a Unicode scalar value".to_string(), i.clone()))
+}
+
+pub fn char_digit_value(c: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(digit_value(c.character()?)
+        .map(|d| Number(d.into()))
+        .unwrap_or(Bool(false)))
+}
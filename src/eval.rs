@@ -1,186 +1,1199 @@
 use crate::ast::LispVal::*;
 use crate::ast::*;
-use std::collections::hash_map::{Entry, HashMap};
+use crate::bitwise;
+use crate::bytevectors;
+use crate::chars;
+use crate::files;
+use crate::hashtables;
+use crate::lists;
+use crate::parameters;
+use crate::ports;
+use crate::promises;
+use crate::records;
+use crate::streams;
+use crate::strings;
+use crate::vectors;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 
-pub type Env = HashMap<String, LispVal>;
+// An environment is a chain of frames.  Closures share their defining environment rather than
+// copying it, so a function can see itself (and anything defined after it) when it's called.
+#[derive(Clone, Default)]
+pub struct Env(Rc<RefCell<Frame>>);
 
-use LispErr::*;
+#[derive(Default)]
+struct Frame {
+    vars: HashMap<Var, LispVal>,
+    parent: Option<Env>,
+    // Set on the outermost frame of an environment made by null-environment, where only the
+    // special forms are visible
+    syntax_only: bool,
+}
+
+impl Env {
+    pub fn new() -> Env {
+        Env::default()
+    }
+
+    // A new, empty frame whose lookups fall back to this environment
+    pub fn extend(&self) -> Env {
+        Env(Rc::new(RefCell::new(Frame {
+            vars: HashMap::new(),
+            parent: Some(self.clone()),
+            syntax_only: false,
+        })))
+    }
+
+    // An environment without the built-in procedures
+    pub fn null() -> Env {
+        Env(Rc::new(RefCell::new(Frame {
+            syntax_only: true,
+            ..Frame::default()
+        })))
+    }
 
-pub fn eval(env: &mut Env, e: &LispVal) -> Result<LispVal, LispErr> {
-    // pattern matching really sucks on 'Rc's.  This makes pattern matching really suck for ConsList, so
-    // matching on special forms also really sucks.
-    // to get around that, transform the cons list into a slice
-    // problem: Atom("foo") and cons(Atom("foo", Nil)) will both be transformed to [Atom("foo")], so we need to test to see if we're matching on a list or not.
-    match e.iter().collect::<Vec<&LispVal>>().as_slice() {
-        [Number(_)] | [Str(_)] | [Bool(_)] if !e.is_cons() => Ok(e.clone()),
-        [] if *e == Nil => Ok(e.clone()),
-        [Atom(a)] if !e.is_cons() => env
-            .get(a)
-            .cloned()
-            .ok_or_else(|| UnboundVar("Retrieved an unbound variable".to_string(), a.clone())),
-        [Atom(quote), quoted] if quote == "quote" => Ok((*quoted).clone()),
-        [Atom(set), Atom(var), form] if set == "set!" => set_var(env, var.to_string(), form),
-        [Atom(define), Atom(var), form] if define == "define" => {
-            define_var(env, var.to_string(), form)
+    // The outermost frame, which holds the top-level definitions
+    pub fn global(&self) -> Env {
+        match &self.0.borrow().parent {
+            Some(parent) => parent.global(),
+            None => self.clone(),
         }
-        [Atom(define), ConsList(cons), body @ ..] if define == "define" => {
-            let func_name = match cons.as_ref().car.as_ref() {
-                Atom(name) => Ok(name.clone()),
-                _ => Err(BadSpecialForm(
-                    "Functions must have an atom as a name".to_string(),
-                    e.clone(),
-                )),
-            }?;
-            let func = define_func(env, cons.as_ref().cdr.as_ref(), body)?;
-            define_var(env, func_name, &func)
+    }
+
+    fn has_builtins(&self) -> bool {
+        !self.global().0.borrow().syntax_only
+    }
+
+    pub fn get(&self, var: &Var) -> Option<LispVal> {
+        let frame = self.0.borrow();
+        match frame.vars.get(var) {
+            Some(val) => Some(val.clone()),
+            None => frame.parent.as_ref().and_then(|parent| parent.get(var)),
         }
-        [Atom(iff), cond, if_branch, else_branch] if iff == "if" => match eval(env, cond) {
-            Ok(Bool(true)) => eval(env, if_branch),
-            Ok(Bool(false)) => eval(env, else_branch),
-            Ok(expr) => Err(TypeMismatch(
-                "if's condition must evaluate to a boolean".to_string(),
-                expr,
-            )),
-            e @ Err(_) => e,
-        },
-        [Atom(a), ..] => {
-            let args = eval_args(env, &(e.cdr()?))?;
-            apply(&a, &args)
+    }
+
+    pub fn define<V: Into<Var>>(&self, var: V, val: LispVal) {
+        self.0.borrow_mut().vars.insert(var.into(), val);
+    }
+
+    // Replaces the innermost binding of `var`, returning false if it isn't bound at all
+    pub fn set(&self, var: &Var, val: LispVal) -> bool {
+        let mut frame = self.0.borrow_mut();
+        match frame.vars.get_mut(var) {
+            Some(binding) => {
+                *binding = val;
+                true
+            }
+            None => match &frame.parent {
+                Some(parent) => parent.set(var, val),
+                None => false,
+            },
         }
-        _ => Err(BadSpecialForm(
-            "Unrecognized special form".to_string(),
-            e.clone(),
-        )),
+    }
+
+    // The bindings made directly in this frame, sorted by name
+    pub fn bindings(&self) -> Vec<(Var, LispVal)> {
+        let mut bindings: Vec<(Var, LispVal)> = self
+            .0
+            .borrow()
+            .vars
+            .iter()
+            .map(|(var, val)| (var.clone(), val.clone()))
+            .collect();
+        bindings.sort_by(|x, y| x.0.name().cmp(y.0.name()));
+        bindings
+    }
+}
+
+// Environments are compared by identity.  Comparing (or printing) their contents would never
+// finish, since a closure defined in an environment is usually bound in it too.
+impl PartialEq for Env {
+    fn eq(&self, other: &Env) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Env {}
+
+impl Hash for Env {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0).hash(state)
+    }
+}
+
+impl fmt::Debug for Env {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self
+            .bindings()
+            .into_iter()
+            .map(|(var, _)| var.to_string())
+            .collect();
+        f.debug_tuple("Env").field(&names).finish()
+    }
+}
+
+use LispErr::*;
+
+thread_local! {
+    // The top level of the outermost evaluation in progress, which is what
+    // interaction-environment returns however it's called
+    static INTERACTION_ENV: RefCell<Option<Env>> = const { RefCell::new(None) };
+}
+
+pub fn eval(env: &Env, e: &LispVal) -> Result<LispVal, LispErr> {
+    if INTERACTION_ENV.with(|i| i.borrow().is_some()) {
+        return eval_loop(env, e);
+    }
+    INTERACTION_ENV.with(|i| *i.borrow_mut() = Some(env.global()));
+    let result = eval_loop(env, e);
+    INTERACTION_ENV.with(|i| *i.borrow_mut() = None);
+    result
+}
+
+// Tail calls don't grow the Rust stack: rather than recursing to evaluate an if's branch or the
+// last form in a function's body, eval loops round with the new expression and environment.
+fn eval_loop(env: &Env, e: &LispVal) -> Result<LispVal, LispErr> {
+    let mut env = env.clone();
+    let mut e = e.clone();
+    loop {
+        // pattern matching really sucks on 'Rc's.  This makes pattern matching really suck for ConsList, so
+        // matching on special forms also really sucks.
+        // to get around that, transform the cons list into a slice
+        // problem: Atom("foo") and cons(Atom("foo", Nil)) will both be transformed to [Atom("foo")], so we need to test to see if we're matching on a list or not.
+        let (next_env, next_e) = match e.iter().collect::<Vec<LispVal>>().as_slice() {
+            [Number(_)]
+            | [Str(_)]
+            | [Bool(_)]
+            | [Char(_)]
+            | [Keyword(_)]
+            | [Vector(_)]
+            | [Bytevector(_)]
+                if !e.is_cons() =>
+            {
+                return Ok(e.clone())
+            }
+            [] if e == Nil => return Ok(e.clone()),
+            [Atom(_)] | [Uninterned(_)] if !e.is_cons() => {
+                let var = e.var()?;
+                let primitive = || match &var {
+                    Var::Interned(name) => builtin(&env, name),
+                    Var::Uninterned(_) => None,
+                };
+                return env.get(&var).or_else(primitive).ok_or_else(|| {
+                    UnboundVar("Retrieved an unbound variable".to_string(), var.to_string())
+                });
+            }
+            [Atom(quote), quoted] if quote == "quote" => return Ok((*quoted).clone()),
+            [Atom(set), var @ (Atom(_) | Uninterned(_)), form] if set == "set!" => {
+                let val = eval(&env, form)?;
+                return set_var(&env, var.var()?, &val);
+            }
+            [Atom(define), var @ (Atom(_) | Uninterned(_)), form] if define == "define" => {
+                let val = eval(&env, form)?;
+                return define_var(&env, var.var()?, &val);
+            }
+            [Atom(define), ConsList(cons), body @ ..]
+                if define == "define" || define == "define*" =>
+            {
+                let func_name = cons.car().var().map_err(|_| {
+                    BadSpecialForm(
+                        "Functions must have an atom as a name".to_string(),
+                        e.clone(),
+                    )
+                })?;
+                let func = define_func(&env, &cons.cdr(), body)?;
+                return define_var(&env, func_name, &func);
+            }
+            [Atom(define), spec @ ..] if define == "define-record-type" => {
+                return records::define_record_type(&env, spec)
+            }
+            [Atom(define), formals, form] if define == "define-values" => {
+                let val = eval(&env, form)?;
+                bind_formals(&env, formals, &values_of(val))?;
+                return Ok(Unspecified);
+            }
+            [Atom(let_values), bindings, body @ ..]
+                if let_values == "let-values" || let_values == "let*-values" =>
+            {
+                // let*-values evaluates each binding's expression with the earlier ones in scope
+                let inner = env.extend();
+                for binding in bindings.list_items()? {
+                    let (formals, form) = match binding.list_items()?.as_slice() {
+                        [formals, form] => (formals.clone(), form.clone()),
+                        _ => {
+                            return Err(BadSpecialForm(
+                                "Expected a (formals expression) binding".to_string(),
+                                binding,
+                            ))
+                        }
+                    };
+                    let scope = if let_values == "let*-values" {
+                        &inner
+                    } else {
+                        &env
+                    };
+                    let vals = values_of(eval(scope, &form)?);
+                    bind_formals(&inner, &formals, &vals)?;
+                }
+                let last = body_tail(&inner, body)?;
+                (inner, last)
+            }
+            [Atom(receive), formals, form, body @ ..] if receive == "receive" => {
+                let vals = values_of(eval(&env, form)?);
+                let inner = env.extend();
+                bind_formals(&inner, formals, &vals)?;
+                let last = body_tail(&inner, body)?;
+                (inner, last)
+            }
+            [Atom(parameterize), bindings, body @ ..] if parameterize == "parameterize" => {
+                let mut params = Vec::new();
+                for binding in bindings.list_items()? {
+                    match binding.list_items()?.as_slice() {
+                        [param, val] => params.push((eval(&env, param)?, eval(&env, val)?)),
+                        _ => {
+                            return Err(BadSpecialForm(
+                                "Expected a (parameter value) binding".to_string(),
+                                binding,
+                            ))
+                        }
+                    }
+                }
+                // the body isn't in tail position, since the old values are restored after it
+                let inner = env.extend();
+                return parameters::parameterize(&params, || {
+                    let last = body_tail(&inner, body)?;
+                    eval(&inner, &last)
+                });
+            }
+            [Atom(guard), spec, body @ ..] if guard == "guard" => {
+                let (var, clauses) = match spec.list_items()?.split_first() {
+                    Some((var @ (Atom(_) | Uninterned(_)), clauses)) => {
+                        (var.var()?, clauses.to_vec())
+                    }
+                    _ => {
+                        return Err(BadSpecialForm(
+                            "Expected (guard (var clause ...) body ...)".to_string(),
+                            e.clone(),
+                        ))
+                    }
+                };
+                let body_env = env.extend();
+                let err = match body_tail(&body_env, body).and_then(|last| eval(&body_env, &last)) {
+                    Ok(val) => return Ok(val),
+                    Err(err) => err,
+                };
+                let inner = env.extend();
+                inner.define(var, ErrorObject(Rc::new(err.clone())));
+                match guard_clause(&inner, &clauses)? {
+                    Some(GuardClause::Value(val)) => return Ok(val),
+                    Some(GuardClause::Body(body)) => {
+                        let last = body_tail(&inner, &body)?;
+                        (inner, last)
+                    }
+                    // no clause handles the error, so it carries on out of the guard
+                    None => return Err(err),
+                }
+            }
+            [Atom(delay), form] if delay == "delay" => {
+                return Ok(promises::delay(thunk(&env, form)))
+            }
+            [Atom(delay), form] if delay == "delay-force" => {
+                return Ok(promises::delay_force(thunk(&env, form)))
+            }
+            [Atom(cons), car, cdr] if cons == "stream-cons" => {
+                let car = promises::delay(thunk(&env, car));
+                let cdr = promises::stream_lazy(thunk(&env, cdr));
+                return Ok(streams::stream_pair(car, cdr));
+            }
+            [Atom(lambda), params, body @ ..] if lambda == "lambda" || lambda == "lambda*" => {
+                return define_func(&env, params, body)
+            }
+            [Atom(case_lambda), clauses @ ..] if case_lambda == "case-lambda" => {
+                let clauses = clauses
+                    .iter()
+                    .map(|clause| match clause {
+                        ConsList(cons) => define_func(&env, &cons.car(), &cons.cdr().list_items()?),
+                        _ => Err(BadSpecialForm(
+                            "Expected a (formals body ...) clause".to_string(),
+                            clause.clone(),
+                        )),
+                    })
+                    .collect::<Result<Vec<LispVal>, LispErr>>()?;
+                return Ok(CaseLambda(clauses));
+            }
+            [Atom(iff), cond, if_branch, else_branch] if iff == "if" => match eval(&env, cond)? {
+                Bool(true) => (env.clone(), if_branch.clone()),
+                Bool(false) => (env.clone(), else_branch.clone()),
+                expr => {
+                    return Err(TypeMismatch(
+                        "if's condition must evaluate to a boolean".to_string(),
+                        expr,
+                    ))
+                }
+            },
+            [func, ..] if e.is_cons() => {
+                let mut func = eval(&env, func)?;
+                let mut args = eval_args(&env, &(e.cdr()?))?;
+                // (apply f args) and (call-with-values producer consumer) in tail position are
+                // tail calls to f and the consumer
+                loop {
+                    match &func {
+                        PrimitiveFunc(name) if name == "apply" => {
+                            let (f, spread) = spread_args(&args)?;
+                            func = f;
+                            args = spread;
+                        }
+                        PrimitiveFunc(name) if name == "call-with-values" => {
+                            match args.as_slice() {
+                                [producer, consumer] => {
+                                    let vals = values_of(apply(producer, &[])?);
+                                    func = consumer.clone();
+                                    args = vals;
+                                }
+                                _ => return Err(NumArgs(2, LispVal::list(&args))),
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+                if let CaseLambda(clauses) = &func {
+                    func = select_clause(clauses, &args)?;
+                }
+                match &func {
+                    Func {
+                        params,
+                        body,
+                        env: closure_env,
+                    } => {
+                        let call_env = bind_args(closure_env, params, &args)?;
+                        let last = body_tail(&call_env, body)?;
+                        (call_env, last)
+                    }
+                    // (eval expr env) in tail position evaluates expr in tail position
+                    PrimitiveFunc(name) if name == "eval" => match args.as_slice() {
+                        [expr, eval_env] => (environment_of(eval_env)?, expr.clone()),
+                        _ => return Err(NumArgs(2, LispVal::list(&args))),
+                    },
+                    _ => return apply(&func, &args),
+                }
+            }
+            _ => {
+                return Err(BadSpecialForm(
+                    "Unrecognized special form".to_string(),
+                    e.clone(),
+                ))
+            }
+        };
+        env = next_env;
+        e = next_e;
     }
 }
 
-pub fn define_func(env: &mut Env, args: &LispVal, body: &[&LispVal]) -> Result<LispVal, LispErr> {
-    let is_varargs = match args {
-        ConsList(cons) => cons.as_ref().is_dotted(),
-        _ => true, // no regular argument, just the vararg
+// Parses a lambda list like (a b . rest), or one with DSSSL-style markers like
+// (a #!optional (b 1) #!key (c 2) #!rest r).  Optional and keyword parameters are written either
+// as a name, which defaults to #f, or as (name default).  lambda accepts the same lists as
+// lambda*, as in MIT Scheme.
+fn parse_params(formals: &LispVal) -> Result<Params, LispErr> {
+    let bad_params = || BadSpecialForm("Malformed parameter list".to_string(), formals.clone());
+    let with_default = |param: &LispVal| match param {
+        Atom(_) | Uninterned(_) => Ok((param.var()?, Bool(false))),
+        _ => match param.list_items().as_deref() {
+            Ok([name @ (Atom(_) | Uninterned(_)), default]) => Ok((name.var()?, default.clone())),
+            _ => Err(bad_params()),
+        },
     };
-    let mut params: Vec<String> = args.iter().map(|l| format!("{}", l)).collect();
-    let vararg: Option<String> = if is_varargs { params.pop() } else { None };
 
+    let mut params = Params::default();
+    let mut section = String::new();
+    let mut tail = formals.clone();
+    loop {
+        let param = match tail {
+            ConsList(cons) => {
+                tail = cons.cdr();
+                cons.car()
+            }
+            Nil => break,
+            Atom(_) | Uninterned(_) if params.rest.is_none() => {
+                params.rest = Some(tail.var()?);
+                break;
+            }
+            _ => return Err(bad_params()),
+        };
+        match (&param, section.as_str()) {
+            (Atom(marker), _) if ["#!optional", "#!key", "#!rest"].contains(&marker.as_str()) => {
+                section = marker.clone()
+            }
+            (Atom(_) | Uninterned(_), "") => params.required.push(param.var()?),
+            (_, "#!optional") => params.optional.push(with_default(&param)?),
+            (_, "#!key") => params.keys.push(with_default(&param)?),
+            (Atom(_) | Uninterned(_), "#!rest") if params.rest.is_none() => {
+                params.rest = Some(param.var()?)
+            }
+            _ => return Err(bad_params()),
+        }
+    }
+    Ok(params)
+}
+
+pub fn define_func(env: &Env, args: &LispVal, body: &[LispVal]) -> Result<LispVal, LispErr> {
     Ok(Func {
-        params: params,
-        vararg: vararg,
-        body: body.into_iter().map(|l| (*l).clone()).collect(),
+        params: Rc::new(parse_params(args)?),
+        body: body.to_vec(),
         env: env.clone(),
     })
 }
 
-pub fn define_var(env: &mut Env, var: String, form: &LispVal) -> Result<LispVal, LispErr> {
-    Ok(env
-        .entry(var)
-        .and_modify(|e| *e = form.clone())
-        .or_insert_with(|| form.clone())
-        .clone())
+// Picks the first of a case-lambda's clauses that takes this many arguments.
+fn select_clause(clauses: &[LispVal], args: &[LispVal]) -> Result<LispVal, LispErr> {
+    clauses
+        .iter()
+        .find(|clause| matches!(clause, Func { params, .. } if params.accepts(args.len())))
+        .cloned()
+        .ok_or_else(|| {
+            Default(format!(
+                "No case-lambda clause takes {} arguments",
+                args.len()
+            ))
+        })
 }
 
-pub fn set_var(env: &mut Env, var: String, form: &LispVal) -> Result<LispVal, LispErr> {
-    match env.entry(var) {
-        Entry::Occupied(mut entry) => {
-            entry.insert(form.clone());
-            Ok(form.clone())
-        }
-        Entry::Vacant(_) => Err(UnboundVar("".to_string(), "".to_string())), //todo: err message
+pub fn define_var(env: &Env, var: Var, form: &LispVal) -> Result<LispVal, LispErr> {
+    env.define(var, form.clone());
+    Ok(Unspecified)
+}
+
+pub fn set_var(env: &Env, var: Var, form: &LispVal) -> Result<LispVal, LispErr> {
+    if env.set(&var, form.clone()) {
+        Ok(Unspecified)
+    } else {
+        Err(UnboundVar(
+            "Setting an unbound variable".to_string(),
+            var.to_string(),
+        ))
     }
 }
 
-pub fn eval_args(env: &mut Env, args: &LispVal) -> Result<Vec<LispVal>, LispErr> {
+// Each argument must be a single value: multiple values can only be passed on by
+// call-with-values and friends.
+pub fn eval_args(env: &Env, args: &LispVal) -> Result<Vec<LispVal>, LispErr> {
     let mut v = Vec::new();
 
     for arg in args.iter() {
-        v.push(eval(env, arg)?);
+        match eval(env, &arg)? {
+            vals @ Values(_) => {
+                return Err(TypeMismatch("Expected a single value".to_string(), vals))
+            }
+            val => v.push(val),
+        }
     }
 
     Ok(v)
 }
 
-pub fn apply(func: &str, args: &[LispVal]) -> Result<LispVal, LispErr> {
-    apply_prim(func, args).unwrap_or_else(|| {
-        Err(NotFunction(
-            func.to_string(),
+pub fn apply(func: &LispVal, args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match func {
+        PrimitiveFunc(name) => apply_prim(name, args).unwrap_or_else(|| {
+            Err(NotFunction(
+                name.to_string(),
+                "is not a function".to_string(),
+            ))
+        }),
+        Func { params, body, env } => {
+            let env = bind_args(env, params, args)?;
+            let mut res = Nil;
+            for form in body {
+                res = eval(&env, form)?;
+            }
+            Ok(res)
+        }
+        CaseLambda(clauses) => apply(&select_clause(clauses, args)?, args),
+        RecordProc(proc) => records::apply_record_proc(proc, args),
+        Parameter(param) => parameters::apply_parameter(param, args),
+        _ => Err(NotFunction(
+            format!("{}", func),
             "is not a function".to_string(),
-        ))
-    })
+        )),
+    }
+}
+
+// Binds parameters to arguments in `env`.  Optional arguments are taken by position, stopping at
+// the first keyword if the procedure has keyword parameters, and the rest parameter gets whatever
+// follows them, keywords included.  Defaults are evaluated in `env`, so they can refer to the
+// parameters before them.
+fn define_params(env: &Env, params: &Params, args: &[LispVal]) -> Result<(), LispErr> {
+    let required = params.required.len();
+    if !params.accepts(args.len()) {
+        return Err(NumArgs(required as i32, LispVal::list(args)));
+    }
+    for (param, arg) in params.required.iter().zip(args) {
+        env.define(param.clone(), arg.clone());
+    }
+    let mut rest = &args[required..];
+    for (param, default) in &params.optional {
+        let val = match rest.split_first() {
+            Some((arg, tail)) if params.keys.is_empty() || !matches!(arg, Keyword(_)) => {
+                rest = tail;
+                arg.clone()
+            }
+            _ => eval(env, default)?,
+        };
+        env.define(param.clone(), val);
+    }
+    if let Some(name) = &params.rest {
+        env.define(name.clone(), LispVal::list(rest));
+    }
+    if params.keys.is_empty() {
+        return Ok(());
+    }
+
+    // the first value given for a keyword wins
+    let mut given = vec![None; params.keys.len()];
+    for pair in rest.chunks(2) {
+        match pair {
+            [Keyword(key), val] => {
+                match params.keys.iter().position(|(name, _)| name.name() == key) {
+                    Some(i) => {
+                        given[i].get_or_insert_with(|| val.clone());
+                    }
+                    None => {
+                        return Err(TypeMismatch(
+                            "Unknown keyword argument".to_string(),
+                            pair[0].clone(),
+                        ))
+                    }
+                }
+            }
+            _ => {
+                return Err(TypeMismatch(
+                    "Expected a keyword followed by its value".to_string(),
+                    pair[0].clone(),
+                ))
+            }
+        }
+    }
+    for ((param, default), val) in params.keys.iter().zip(given) {
+        let val = match val {
+            Some(val) => val,
+            None => eval(env, default)?,
+        };
+        env.define(param.clone(), val);
+    }
+    Ok(())
+}
+
+// Binds a function's parameters to its arguments, in a new frame of the environment it closed over.
+fn bind_args(env: &Env, params: &Params, args: &[LispVal]) -> Result<Env, LispErr> {
+    let env = env.extend();
+    define_params(&env, params, args)?;
+    Ok(env)
+}
+
+// Binds the formals of let-values, receive or define-values, which are written like a lambda list,
+// to a list of values.
+fn bind_formals(env: &Env, formals: &LispVal, vals: &[LispVal]) -> Result<(), LispErr> {
+    define_params(env, &parse_params(formals)?, vals)
+}
+
+// Evaluates all but the last form of a body, returning the last one for the caller to evaluate in
+// tail position.
+fn body_tail(env: &Env, body: &[LispVal]) -> Result<LispVal, LispErr> {
+    match body.split_last() {
+        Some((last, init)) => {
+            for form in init {
+                eval(env, form)?;
+            }
+            Ok(last.clone())
+        }
+        None => Ok(Nil),
+    }
+}
+
+// A single value is just itself; any other number of values is wrapped up in Values.
+fn values(vals: &[LispVal]) -> LispVal {
+    match vals {
+        [val] => val.clone(),
+        _ => Values(vals.to_vec()),
+    }
+}
+
+fn values_of(val: LispVal) -> Vec<LispVal> {
+    match val {
+        Values(vals) => vals,
+        val => vec![val],
+    }
+}
+
+// (floor/ n d) and (truncate/ n d) return the quotient and remainder as two values.
+fn integer_division<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
+where
+    F: Fn(&BigInt, &BigInt) -> (BigInt, BigInt),
+{
+    match args {
+        [_, Number(d)] if d.is_zero() => Err(Default("Division by zero".to_string())),
+        _ => binary_numeric_op(
+            |n, d| {
+                let (q, r) = f(n, d);
+                values(&[Number(q), Number(r)])
+            },
+            args,
+        ),
+    }
+}
+
+fn exact_integer_sqrt(n: &LispVal) -> Result<LispVal, LispErr> {
+    let int = n.integer()?;
+    if int.is_negative() {
+        return Err(OutOfRange(
+            "Expected a non-negative integer".to_string(),
+            n.clone(),
+        ));
+    }
+    let root = int.sqrt();
+    let rest = &int - &root * &root;
+    Ok(values(&[Number(root), Number(rest)]))
+}
+
+// (apply f a b '(c d)) calls f with the arguments a, b, c and d.
+fn spread_args(args: &[LispVal]) -> Result<(LispVal, Vec<LispVal>), LispErr> {
+    match args {
+        [f, init @ .., last] => {
+            let mut spread = init.to_vec();
+            spread.extend(last.list_items()?);
+            Ok((f.clone(), spread))
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+// Primitives are looked up by name whenever a symbol isn't bound in the environment, so they can
+// be passed around like any other procedure.
+pub type Primitive = fn(&[LispVal]) -> Result<LispVal, LispErr>;
+
+enum GuardClause {
+    // a clause with only a test returns the test's value
+    Value(LispVal),
+    Body(Vec<LispVal>),
+}
+
+// Finds the first of a guard's (test body ...), (test => receiver) or (else body ...) clauses that
+// applies.  A receiver is called with the test's value.
+fn guard_clause(env: &Env, clauses: &[LispVal]) -> Result<Option<GuardClause>, LispErr> {
+    for clause in clauses {
+        match clause.list_items()?.split_first() {
+            Some((Atom(els), body)) if els == "else" => {
+                return Ok(Some(GuardClause::Body(body.to_vec())))
+            }
+            Some((test, body)) => match eval(env, test)? {
+                Bool(false) => {}
+                val if body.is_empty() => return Ok(Some(GuardClause::Value(val))),
+                val => match body {
+                    [Atom(arrow), receiver] if arrow == "=>" => {
+                        let receiver = eval(env, receiver)?;
+                        return Ok(Some(GuardClause::Value(apply(&receiver, &[val])?)));
+                    }
+                    [Atom(arrow), ..] if arrow == "=>" => {
+                        return Err(BadSpecialForm(
+                            "Expected a (test => receiver) clause".to_string(),
+                            clause.clone(),
+                        ))
+                    }
+                    _ => return Ok(Some(GuardClause::Body(body.to_vec()))),
+                },
+            },
+            None => {
+                return Err(BadSpecialForm(
+                    "Expected a (test body ...) clause".to_string(),
+                    clause.clone(),
+                ))
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Evaluates `e` in `env` when a promise is forced.
+fn thunk(env: &Env, e: &LispVal) -> promises::Thunk {
+    let env = env.clone();
+    let e = e.clone();
+    Rc::new(move || eval(&env, &e))
+}
+
+// A built-in procedure or constant, unless the environment was made by null-environment
+fn builtin(env: &Env, name: &str) -> Option<LispVal> {
+    if !env.has_builtins() {
+        return None;
+    }
+    primitive(name)
+        .map(|_| PrimitiveFunc(name.to_string()))
+        .or_else(|| constant(name))
+}
+
+// Like primitives, constants are looked up whenever a symbol isn't bound in the environment.
+fn constant(name: &str) -> Option<LispVal> {
+    match name {
+        "stream-null" => Some(streams::stream_null()),
+        "current-input-port" => Some(ports::current_input_port()),
+        "current-output-port" => Some(ports::current_output_port()),
+        "current-error-port" => Some(ports::current_error_port()),
+        _ => None,
+    }
+}
+
+fn interaction_environment(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    if !args.is_empty() {
+        return Err(NumArgs(0, LispVal::list(args)));
+    }
+    INTERACTION_ENV
+        .with(|i| i.borrow().clone())
+        .map(Environment)
+        .ok_or_else(|| Default("interaction-environment called outside of eval".to_string()))
 }
 
 pub fn apply_prim(func: &str, args: &[LispVal]) -> Option<Result<LispVal, LispErr>> {
-    match func {
-        "debug" => Some(print_debug(args)),
-        "+" => Some(monoidal_numeric_op(|x, y| x + y, 0, args)),
-        "*" => Some(monoidal_numeric_op(|x, y| x * y, 1, args)),
-        "quotient" => Some(binary_numeric_op(|x, y| Number(x / y), args)),
-        "remainder" => Some(binary_numeric_op(|x, y| Number(x % y), args)),
-        "mod" => Some(binary_numeric_op(|x, y| Number(x.rem_euclid(y)), args)),
+    primitive(func).map(|prim| prim(args))
+}
+
+pub fn primitive(name: &str) -> Option<Primitive> {
+    let prim: Primitive = match name {
+        "debug" => print_debug,
+        "eval" => |args| try_binary_op(eval_in, args),
+        "environment" => environment,
+        "interaction-environment" => interaction_environment,
+        "scheme-report-environment" => |args| unary_op(|v| report_environment(v, Env::new()), args),
+        "null-environment" => |args| unary_op(|v| report_environment(v, Env::null()), args),
+        "error-object?" => |args| unary_op(|e| Ok(Bool(matches!(e, ErrorObject(_)))), args),
+        "file-error?" => |args| {
+            unary_op(
+                |e| {
+                    Ok(Bool(
+                        matches!(e, ErrorObject(err) if matches!(**err, FileError(..))),
+                    ))
+                },
+                args,
+            )
+        },
+        "read-error?" => |args| {
+            unary_op(
+                |e| {
+                    Ok(Bool(
+                        matches!(e, ErrorObject(err) if matches!(**err, ParseError(_))),
+                    ))
+                },
+                args,
+            )
+        },
+        "error-object-message" => {
+            |args| unary_op(|e| Ok(Str(error_object(e)?.message().into())), args)
+        }
+        "error-object-irritants" => {
+            |args| unary_op(|e| Ok(LispVal::list(&error_object(e)?.irritants())), args)
+        }
+        "eof-object" => |args| match args {
+            [] => Ok(Eof),
+            _ => Err(NumArgs(0, LispVal::list(args))),
+        },
+        "eof-object?" => |args| unary_op(|e| Ok(Bool(matches!(e, Eof))), args),
+        "values" => |args| Ok(values(args)),
+        "call-with-values" => |args| match args {
+            [producer, consumer] => apply(consumer, &values_of(apply(producer, &[])?)),
+            _ => Err(NumArgs(2, LispVal::list(args))),
+        },
+        "floor/" => |args| integer_division(|n, d| n.div_mod_floor(d), args),
+        "truncate/" => |args| integer_division(|n, d| (n / d, n % d), args),
+        "exact-integer-sqrt" => |args| unary_op(exact_integer_sqrt, args),
+        "apply" => |args| {
+            let (f, args) = spread_args(args)?;
+            apply(&f, &args)
+        },
+        "+" => |args| monoidal_numeric_op(|x, y| x + y, 0.into(), args),
+        "*" => |args| monoidal_numeric_op(|x, y| x * y, 1.into(), args),
+        "quotient" => |args| binary_numeric_op(|x, y| Number(x / y), args),
+        "remainder" => |args| binary_numeric_op(|x, y| Number(x % y), args),
+        "mod" => |args| binary_numeric_op(|x, y| Number(x.mod_floor(&y.abs())), args),
         // todo: - and / should really take n args and work as negation/reciprocal for 1 arg
-        "-" => Some(binary_numeric_op(|x, y| Number(x - y), args)),
-        "/" => Some(binary_numeric_op(|x, y| Number(x / y), args)),
+        "-" => |args| binary_numeric_op(|x, y| Number(x - y), args),
+        "/" => |args| binary_numeric_op(|x, y| Number(x / y), args),
 
         // todo: these should be n-ary
-        "=" => Some(binary_numeric_op(|x, y| Bool(x == y), args)),
-        ">" => Some(binary_numeric_op(|x, y| Bool(x > y), args)),
-        "<" => Some(binary_numeric_op(|x, y| Bool(x < y), args)),
-        ">=" => Some(binary_numeric_op(|x, y| Bool(x >= y), args)),
-        "<=" => Some(binary_numeric_op(|x, y| Bool(x <= y), args)),
-
-        "string-=?" => Some(binary_string_op(|x, y| Bool(x == y), args)),
-        "string->?" => Some(binary_string_op(|x, y| Bool(x > y), args)),
-        "string-<?" => Some(binary_string_op(|x, y| Bool(x < y), args)),
-        "string->=?" => Some(binary_string_op(|x, y| Bool(x >= y), args)),
-        "string-<=?" => Some(binary_string_op(|x, y| Bool(x <= y), args)),
-
-        "||" => Some(monoidal_op(
-            |x, y| x || y,
-            |b: &LispVal| b.boolean(),
-            |b| Bool(b),
-            true,
-            args,
+        "=" => |args| binary_numeric_op(|x, y| Bool(x == y), args),
+        ">" => |args| binary_numeric_op(|x, y| Bool(x > y), args),
+        "<" => |args| binary_numeric_op(|x, y| Bool(x < y), args),
+        ">=" => |args| binary_numeric_op(|x, y| Bool(x >= y), args),
+        "<=" => |args| binary_numeric_op(|x, y| Bool(x <= y), args),
+
+        // SRFI 151 bitwise operations, treating integers as infinite two's complement bit strings
+        "bitwise-not" => |args| unary_op(|x| Ok(Number(!x.integer()?)), args),
+        "bitwise-and" => |args| monoidal_numeric_op(|x, y| x & y, (-1).into(), args),
+        "bitwise-ior" | "bitwise-or" => |args| monoidal_numeric_op(|x, y| x | y, 0.into(), args),
+        "bitwise-xor" => |args| monoidal_numeric_op(|x, y| x ^ y, 0.into(), args),
+        "bitwise-eqv" => |args| monoidal_numeric_op(|x, y| !(x ^ y), (-1).into(), args),
+        "bitwise-nand" => |args| binary_numeric_op(|x, y| Number(!(x & y)), args),
+        "bitwise-nor" => |args| binary_numeric_op(|x, y| Number(!(x | y)), args),
+        "bitwise-andc1" => |args| binary_numeric_op(|x, y| Number(!x & y), args),
+        "bitwise-andc2" => |args| binary_numeric_op(|x, y| Number(x & !y), args),
+        "bitwise-orc1" => |args| binary_numeric_op(|x, y| Number(!x | y), args),
+        "bitwise-orc2" => |args| binary_numeric_op(|x, y| Number(x | !y), args),
+        "arithmetic-shift" => |args| try_binary_op(bitwise::arithmetic_shift, args),
+        "bit-count" => |args| unary_op(bitwise::bit_count, args),
+        "integer-length" => |args| unary_op(bitwise::integer_length, args),
+        "bitwise-if" => |args| ternary_op(bitwise::bitwise_if, args),
+        "bit-set?" => |args| try_binary_op(bitwise::bit_set, args),
+        "copy-bit" => |args| ternary_op(bitwise::copy_bit, args),
+        "bit-swap" => |args| ternary_op(bitwise::bit_swap, args),
+        "any-bit-set?" => |args| binary_numeric_op(|x, y| Bool(x & y != 0.into()), args),
+        "every-bit-set?" => |args| binary_numeric_op(|x, y| Bool(&(x & y) == x), args),
+        "first-set-bit" => |args| unary_op(bitwise::first_set_bit, args),
+        "bit-field" => |args| ternary_op(bitwise::bit_field, args),
+
+        "string=?" | "string-=?" => |args| comparison_op(|x, y| x == y, LispVal::string, args),
+        "string<?" | "string-<?" => |args| comparison_op(|x, y| x < y, LispVal::string, args),
+        "string>?" | "string->?" => |args| comparison_op(|x, y| x > y, LispVal::string, args),
+        "string<=?" | "string-<=?" => |args| comparison_op(|x, y| x <= y, LispVal::string, args),
+        "string>=?" | "string->=?" => |args| comparison_op(|x, y| x >= y, LispVal::string, args),
+        "string-length" => |args| unary_op(strings::string_length, args),
+        "string-ref" => |args| try_binary_op(strings::string_ref, args),
+        "substring" => strings::substring,
+        "string-append" => strings::string_append,
+        "string-copy" => strings::string_copy,
+        "string->list" => strings::string_to_list,
+        "list->string" => |args| unary_op(strings::list_to_string, args),
+        "string->symbol" => |args| unary_op(|s| Ok(Atom(s.string()?.to_string())), args),
+        "symbol->string" => |args| unary_op(|s| Ok(Str(s.symbol()?.into())), args),
+        "string-upcase" => |args| unary_op(strings::string_upcase, args),
+        "string-downcase" => |args| unary_op(strings::string_downcase, args),
+        "string-foldcase" => |args| unary_op(strings::string_foldcase, args),
+        "string-normalize-nfc" => |args| unary_op(strings::string_normalize_nfc, args),
+        "string-normalize-nfd" => |args| unary_op(strings::string_normalize_nfd, args),
+        "string-normalize-nfkc" => |args| unary_op(strings::string_normalize_nfkc, args),
+        "string-normalize-nfkd" => |args| unary_op(strings::string_normalize_nfkd, args),
+        "string-graphemes" => |args| unary_op(strings::string_graphemes, args),
+        "string-grapheme-count" => |args| unary_op(strings::string_grapheme_count, args),
+        "string-index" => strings::string_index,
+        "string-contains" => |args| try_binary_op(strings::string_contains, args),
+        "string-join" => strings::string_join,
+        "string-split" => strings::string_split,
+
+        "make-vector" => vectors::make_vector,
+        "vector" => vectors::vector,
+        "vector-length" => |args| unary_op(vectors::vector_length, args),
+        "vector-ref" => |args| try_binary_op(vectors::vector_ref, args),
+        "vector-set!" => |args| ternary_op(vectors::vector_set, args),
+        "vector->list" => vectors::vector_to_list,
+        "list->vector" => |args| unary_op(vectors::list_to_vector, args),
+        "vector-fill!" => vectors::vector_fill,
+        "vector-copy" => vectors::vector_copy,
+        "vector-append" => vectors::vector_append,
+        "vector-map" => vectors::vector_map,
+        "vector-for-each" => vectors::vector_for_each,
+
+        "bytevector?" => |args| unary_op(|bv| Ok(Bool(matches!(bv, Bytevector(_)))), args),
+        "make-bytevector" => bytevectors::make_bytevector,
+        "bytevector" => bytevectors::bytevector,
+        "bytevector-length" => |args| unary_op(bytevectors::bytevector_length, args),
+        "bytevector-u8-ref" => |args| try_binary_op(bytevectors::bytevector_u8_ref, args),
+        "bytevector-u8-set!" => |args| ternary_op(bytevectors::bytevector_u8_set, args),
+        "bytevector-copy" => bytevectors::bytevector_copy,
+        "bytevector-copy!" => bytevectors::bytevector_copy_into,
+        "bytevector-append" => bytevectors::bytevector_append,
+        "utf8->string" => bytevectors::utf8_to_string,
+        "string->utf8" => bytevectors::string_to_utf8,
+        "native-endianness" => bytevectors::native_endianness,
+        "bytevector-u16-ref" => |args| bytevectors::int_ref(2, false, args),
+        "bytevector-s16-ref" => |args| bytevectors::int_ref(2, true, args),
+        "bytevector-u32-ref" => |args| bytevectors::int_ref(4, false, args),
+        "bytevector-s32-ref" => |args| bytevectors::int_ref(4, true, args),
+        "bytevector-u64-ref" => |args| bytevectors::int_ref(8, false, args),
+        "bytevector-s64-ref" => |args| bytevectors::int_ref(8, true, args),
+        "bytevector-u16-set!" => |args| bytevectors::int_set(2, false, args),
+        "bytevector-s16-set!" => |args| bytevectors::int_set(2, true, args),
+        "bytevector-u32-set!" => |args| bytevectors::int_set(4, false, args),
+        "bytevector-s32-set!" => |args| bytevectors::int_set(4, true, args),
+        "bytevector-u64-set!" => |args| bytevectors::int_set(8, false, args),
+        "bytevector-s64-set!" => |args| bytevectors::int_set(8, true, args),
+
+        "hash-table?" => |args| unary_op(|t| Ok(Bool(matches!(t, HashTable(_)))), args),
+        "make-hash-table" => hashtables::make_hash_table,
+        "hash-table-ref" => hashtables::hash_table_ref,
+        "hash-table-ref/default" => |args| ternary_op(hashtables::hash_table_ref_default, args),
+        "hash-table-set!" => |args| ternary_op(hashtables::hash_table_set, args),
+        "hash-table-delete!" => |args| try_binary_op(hashtables::hash_table_delete, args),
+        "hash-table-exists?" => |args| try_binary_op(hashtables::hash_table_contains, args),
+        "hash-table-contains?" => |args| try_binary_op(hashtables::hash_table_contains, args),
+        "hash-table-update!" => hashtables::hash_table_update,
+        "hash-table-update!/default" => hashtables::hash_table_update_default,
+        "hash-table-size" => |args| unary_op(hashtables::hash_table_size, args),
+        "hash-table-keys" => |args| unary_op(hashtables::hash_table_keys, args),
+        "hash-table-values" => |args| unary_op(hashtables::hash_table_values, args),
+        "hash-table-walk" => |args| try_binary_op(hashtables::hash_table_walk, args),
+        "hash-table->alist" => |args| unary_op(hashtables::hash_table_to_alist, args),
+        "alist->hash-table" => hashtables::alist_to_hash_table,
+        "hash-table-copy" => |args| unary_op(hashtables::hash_table_copy, args),
+        "hash-table-clear!" => |args| unary_op(hashtables::hash_table_clear, args),
+
+        "char?" => |args| unary_op(|c| Ok(Bool(matches!(c, Char(_)))), args),
+        "char->integer" => |args| unary_op(chars::char_to_integer, args),
+        "integer->char" => |args| unary_op(chars::integer_to_char, args),
+        "char=?" => |args| comparison_op(|x, y| x == y, LispVal::character, args),
+        "char<?" => |args| comparison_op(|x, y| x < y, LispVal::character, args),
+        "char>?" => |args| comparison_op(|x, y| x > y, LispVal::character, args),
+        "char<=?" => |args| comparison_op(|x, y| x <= y, LispVal::character, args),
+        "char>=?" => |args| comparison_op(|x, y| x >= y, LispVal::character, args),
+        "char-ci=?" => |args| comparison_op(|x, y| x == y, folded_char, args),
+        "char-ci<?" => |args| comparison_op(|x, y| x < y, folded_char, args),
+        "char-ci>?" => |args| comparison_op(|x, y| x > y, folded_char, args),
+        "char-ci<=?" => |args| comparison_op(|x, y| x <= y, folded_char, args),
+        "char-ci>=?" => |args| comparison_op(|x, y| x >= y, folded_char, args),
+        "char-alphabetic?" => |args| char_op(|c| Bool(c.is_alphabetic()), args),
+        "char-numeric?" => |args| char_op(|c| Bool(chars::digit_value(c).is_some()), args),
+        "char-whitespace?" => |args| char_op(|c| Bool(c.is_whitespace()), args),
+        "char-upper-case?" => |args| char_op(|c| Bool(c.is_uppercase()), args),
+        "char-lower-case?" => |args| char_op(|c| Bool(c.is_lowercase()), args),
+        "digit-value" => |args| unary_op(chars::char_digit_value, args),
+        "char-upcase" => |args| char_op(|c| Char(chars::upcase(c)), args),
+        "char-downcase" => |args| char_op(|c| Char(chars::downcase(c)), args),
+        "char-foldcase" => |args| char_op(|c| Char(chars::foldcase(c)), args),
+
+        "||" => |args| monoidal_op(|x, y| x || y, |b: &LispVal| b.boolean(), Bool, true, args),
+        "&&" => |args| monoidal_op(|x, y| x && y, |b: &LispVal| b.boolean(), Bool, false, args),
+
+        "pair?" => |args| unary_op(|p| Ok(Bool(p.is_cons())), args),
+        "null?" => |args| unary_op(|l| Ok(Bool(matches!(l, Nil))), args),
+        "list?" => |args| unary_op(|l| Ok(Bool(matches!(list_shape(l), (_, Some(Nil))))), args),
+        "symbol?" => |args| unary_op(|s| Ok(Bool(matches!(s, Atom(_) | Uninterned(_)))), args),
+        "string?" => |args| unary_op(|s| Ok(Bool(matches!(s, Str(_)))), args),
+        "number?" => |args| unary_op(|n| Ok(Bool(matches!(n, Number(_)))), args),
+        "boolean?" => |args| unary_op(|b| Ok(Bool(matches!(b, Bool(_)))), args),
+        "procedure?" => |args| {
+            unary_op(
+                |f| {
+                    Ok(Bool(matches!(
+                        f,
+                        Func { .. }
+                            | PrimitiveFunc(_)
+                            | CaseLambda(_)
+                            | RecordProc(_)
+                            | Parameter(_)
+                    )))
+                },
+                args,
+            )
+        },
+        "vector?" => |args| unary_op(|v| Ok(Bool(matches!(v, Vector(_)))), args),
+        "keyword?" => |args| unary_op(|k| Ok(Bool(matches!(k, Keyword(_)))), args),
+        "keyword->symbol" => |args| {
+            unary_op(
+                |k| match k {
+                    Keyword(name) => Ok(Atom(name.clone())),
+                    _ => Err(TypeMismatch("Expected a keyword".to_string(), k.clone())),
+                },
+                args,
+            )
+        },
+        "symbol->keyword" => |args| unary_op(|s| Ok(Keyword(s.symbol()?)), args),
+        "symbol=?" => |args| comparison_op(LispVal::is_eq, |s| s.symbol().map(|_| s.clone()), args),
+        "gensym" | "generate-uninterned-symbol" => gensym,
+
+        "eq?" => |args| try_binary_op(|x, y| Ok(Bool(x.is_eq(y))), args),
+        "eqv?" => |args| try_binary_op(|x, y| Ok(Bool(x.is_eqv(y))), args),
+        "equal?" => |args| try_binary_op(|x, y| Ok(Bool(x == y)), args),
+        "cons" => |args| binary_op(LispVal::cons, args),
+        "car" => |args| unary_op(LispVal::car, args),
+        "cdr" => |args| unary_op(LispVal::cdr, args),
+        "set-car!" => |args| try_binary_op(LispVal::set_car, args),
+        "set-cdr!" => |args| try_binary_op(LispVal::set_cdr, args),
+
+        "list" => lists::list,
+        "length" => |args| unary_op(lists::length, args),
+        "append" => lists::append,
+        "reverse" => |args| unary_op(lists::reverse, args),
+        "list-tail" => |args| try_binary_op(lists::list_tail, args),
+        "list-ref" => |args| try_binary_op(lists::list_ref, args),
+        "take" => |args| try_binary_op(lists::take, args),
+        "drop" => |args| try_binary_op(lists::list_tail, args),
+        "last-pair" => |args| unary_op(lists::last_pair, args),
+        "map" => lists::map,
+        "for-each" => lists::for_each,
+        "filter" => |args| try_binary_op(lists::filter, args),
+        "remove" => |args| try_binary_op(lists::remove, args),
+        "partition" => |args| try_binary_op(lists::partition, args),
+        "fold" => lists::fold,
+        "fold-left" => lists::fold_left,
+        "fold-right" => lists::fold_right,
+        "reduce" => |args| ternary_op(lists::reduce, args),
+        "delete" => lists::delete,
+        "delete-duplicates" => lists::delete_duplicates,
+        "memq" => |args| try_binary_op(lists::memq, args),
+        "memv" => |args| try_binary_op(lists::memv, args),
+        "member" => lists::member,
+        "assq" => |args| try_binary_op(lists::assq, args),
+        "assv" => |args| try_binary_op(lists::assv, args),
+        "assoc" => lists::assoc,
+        "iota" => lists::iota,
+        "any" => lists::any,
+        "every" => lists::every,
+
+        "make-parameter" => parameters::make_parameter,
+
+        "port?" => |args| unary_op(ports::is_port, args),
+        "input-port?" => |args| unary_op(ports::is_input_port, args),
+        "output-port?" => |args| unary_op(ports::is_output_port, args),
+        "textual-port?" => |args| unary_op(ports::is_textual_port, args),
+        "binary-port?" => |args| unary_op(ports::is_binary_port, args),
+        "input-port-open?" => |args| unary_op(ports::is_input_port_open, args),
+        "output-port-open?" => |args| unary_op(ports::is_output_port_open, args),
+        "close-port" => |args| unary_op(ports::close_port, args),
+        "close-input-port" => |args| unary_op(ports::close_input_port, args),
+        "close-output-port" => |args| unary_op(ports::close_output_port, args),
+        "read-char" => ports::read_char,
+        "peek-char" => ports::peek_char,
+        "read-line" => ports::read_line,
+        "read-string" => ports::read_string,
+        "read" => ports::read,
+        "char-ready?" => ports::is_char_ready,
+        "read-u8" => ports::read_u8,
+        "peek-u8" => ports::peek_u8,
+        "u8-ready?" => ports::is_u8_ready,
+        "display" => ports::display,
+        "write" => ports::write,
+        "newline" => ports::newline,
+        "write-char" => ports::write_char,
+        "write-string" => ports::write_string,
+        "write-u8" => ports::write_u8,
+        "flush-output-port" => ports::flush_output_port,
+        "open-input-file" => |args| unary_op(files::open_input_file, args),
+        "open-binary-input-file" => |args| unary_op(files::open_binary_input_file, args),
+        "open-output-file" => |args| unary_op(files::open_output_file, args),
+        "open-binary-output-file" => |args| unary_op(files::open_binary_output_file, args),
+        "call-with-input-file" => |args| try_binary_op(files::call_with_input_file, args),
+        "call-with-output-file" => |args| try_binary_op(files::call_with_output_file, args),
+        "with-input-from-file" => |args| try_binary_op(files::with_input_from_file, args),
+        "with-output-to-file" => |args| try_binary_op(files::with_output_to_file, args),
+        "file-exists?" => |args| unary_op(files::file_exists, args),
+        "delete-file" => |args| unary_op(files::delete_file, args),
+        "open-input-string" => |args| unary_op(ports::open_input_string, args),
+        "open-output-string" => ports::open_output_string,
+        "get-output-string" => |args| unary_op(ports::get_output_string, args),
+        "call-with-output-string" => |args| unary_op(ports::call_with_output_string, args),
+        "with-output-to-string" => |args| unary_op(ports::with_output_to_string, args),
+        "with-input-from-string" => |args| try_binary_op(ports::with_input_from_string, args),
+
+        "force" => |args| unary_op(promises::force, args),
+        "make-promise" => |args| unary_op(promises::make_promise, args),
+        "promise?" => |args| unary_op(|p| Ok(Bool(matches!(p, Promise(_)))), args),
+        "stream?" => |args| unary_op(streams::is_stream, args),
+        "stream-null?" => |args| unary_op(streams::is_stream_null, args),
+        "stream-pair?" => |args| unary_op(streams::is_stream_pair, args),
+        "stream-car" => |args| unary_op(streams::stream_car, args),
+        "stream-cdr" => |args| unary_op(streams::stream_cdr, args),
+        "stream-ref" => |args| try_binary_op(streams::stream_ref, args),
+        "stream-take" => |args| try_binary_op(streams::stream_take, args),
+        "stream-map" => |args| try_binary_op(streams::stream_map, args),
+        "stream-filter" => |args| try_binary_op(streams::stream_filter, args),
+        "stream->list" => streams::stream_to_list,
+        "list->stream" => |args| unary_op(streams::list_to_stream, args),
+        _ => return None,
+    };
+    Some(prim)
+}
+
+fn error_object(val: &LispVal) -> Result<Rc<LispErr>, LispErr> {
+    match val {
+        ErrorObject(err) => Ok(err.clone()),
+        _ => Err(TypeMismatch(
+            "Expected an error object".to_string(),
+            val.clone(),
         )),
-        "&&" => Some(monoidal_op(
-            |x, y| x && y,
-            |b: &LispVal| b.boolean(),
-            |b| Bool(b),
-            false,
-            args,
+    }
+}
+
+fn environment_of(val: &LispVal) -> Result<Env, LispErr> {
+    match val {
+        Environment(env) => Ok(env.clone()),
+        _ => Err(TypeMismatch(
+            "Expected an environment".to_string(),
+            val.clone(),
         )),
+    }
+}
 
-        "eq?" => Some(eqv(args)),
-        "eqv?" => Some(eqv(args)),
-        // todo: equal
-        "cons" => Some(binary_op(LispVal::cons, args)),
-        "car" => Some(unary_op(LispVal::car, args)),
-        "cdr" => Some(unary_op(LispVal::cdr, args)),
-        _ => None,
+// (eval expr environment)
+pub fn eval_in(expr: &LispVal, env: &LispVal) -> Result<LispVal, LispErr> {
+    eval(&environment_of(env)?, expr)
+}
+
+// The standard libraries whose procedures are built in.  Every environment sees all the
+// built-ins, so (environment import-set ...) only checks that the libraries exist.
+const LIBRARIES: &[&str] = &[
+    "base",
+    "case-lambda",
+    "char",
+    "eval",
+    "file",
+    "lazy",
+    "r5rs",
+    "read",
+    "repl",
+    "write",
+];
+
+// (environment '(scheme base) ...) makes a new environment for eval.
+pub fn environment(import_sets: &[LispVal]) -> Result<LispVal, LispErr> {
+    for set in import_sets {
+        let known = match set.list_items().as_deref() {
+            Ok([Atom(scheme), Atom(name)]) => {
+                scheme == "scheme" && LIBRARIES.contains(&name.as_str())
+            }
+            _ => false,
+        };
+        if !known {
+            return Err(Default(format!("Unknown library {}", set)));
+        }
     }
+    Ok(Environment(Env::new()))
+}
+
+// The R5RS environments, which only come in version 5
+fn report_environment(version: &LispVal, env: Env) -> Result<LispVal, LispErr> {
+    if version.index()? != 5 {
+        return Err(OutOfRange(
+            "Only version 5 is supported".to_string(),
+            version.clone(),
+        ));
+    }
+    Ok(Environment(env))
 }
 
 pub fn print_debug(args: &[LispVal]) -> Result<LispVal, LispErr> {
     for arg in args.iter() {
         println!("{:?}", arg)
     }
-    Ok(args[0].clone())
+    Ok(Unspecified)
 }
 
-pub fn eqv(args: &[LispVal]) -> Result<LispVal, LispErr> {
-    match args {
-        [Number(x), Number(y)] => Ok(Bool(*x == *y)),
-        [Atom(x), Atom(y)] => Ok(Bool(*x == *y)),
-        [Nil, Nil] => Ok(Bool(true)),
-        [Str(x), Str(y)] => Ok(Bool(*x == *y)),
-        [Bool(x), Bool(y)] => Ok(Bool(*x == *y)),
-        [ConsList(x), ConsList(y)] => {
-            if x.car != y.car {
-                Ok(Bool(false))
-            } else {
-                eqv(&[x.cdr.as_ref().clone(), y.cdr.as_ref().clone()])
-            }
-        }
-        [_, _] => Ok(Bool(false)), // no implicit conversions
-        _ => Err(NumArgs(2, Nil)), // todo: fix err
-    }
+thread_local! {
+    static GENSYM_COUNTER: Cell<u64> = const { Cell::new(0) };
+}
+
+// (gensym [prefix]) makes a new uninterned symbol, named like g1, g2, and so on.  The names are
+// only for printing: it isn't eq? to any other symbol, even one typed in with the same name.
+pub fn gensym(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let prefix = match args {
+        [] => "g".to_string(),
+        [Str(s)] => s.to_string(),
+        [prefix] => prefix.symbol()?,
+        _ => return Err(NumArgs(1, LispVal::list(args))),
+    };
+    let n = GENSYM_COUNTER.with(|counter| {
+        counter.set(counter.get() + 1);
+        counter.get()
+    });
+    Ok(Uninterned(Rc::new(format!("{}{}", prefix, n))))
 }
 
 pub fn unary_op<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
@@ -203,6 +1216,26 @@ where
     }
 }
 
+pub fn try_binary_op<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
+where
+    F: Fn(&LispVal, &LispVal) -> Result<LispVal, LispErr>,
+{
+    match args {
+        [x, y] => f(x, y),
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+pub fn ternary_op<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
+where
+    F: Fn(&LispVal, &LispVal, &LispVal) -> Result<LispVal, LispErr>,
+{
+    match args {
+        [x, y, z] => f(x, y, z),
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
 pub fn binary_string_op<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
 where
     F: Fn(&str, &str) -> LispVal,
@@ -217,12 +1250,23 @@ where
     }
 }
 
+pub fn char_op<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
+where
+    F: Fn(char) -> LispVal,
+{
+    unary_op(|c| Ok(f(c.character()?)), args)
+}
+
+fn folded_char(c: &LispVal) -> Result<char, LispErr> {
+    c.character().map(chars::foldcase)
+}
+
 pub fn binary_numeric_op<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
 where
-    F: Fn(i32, i32) -> LispVal,
+    F: Fn(&BigInt, &BigInt) -> LispVal,
 {
     match args {
-        [Number(x), Number(y)] => Ok(f(*x, *y)),
+        [Number(x), Number(y)] => Ok(f(x, y)),
         [_, _] => Err(TypeMismatch(
             "Wrong type arguments for primive function".to_string(),
             Nil,
@@ -250,11 +1294,25 @@ where
     Ok(to_lispval(res))
 }
 
+// R7RS comparison predicates take any number of arguments, and are true when every adjacent pair
+// of arguments is in order.
+pub fn comparison_op<F, G, A>(f: F, from_lispval: G, args: &[LispVal]) -> Result<LispVal, LispErr>
+where
+    F: Fn(&A, &A) -> bool,
+    G: Fn(&LispVal) -> Result<A, LispErr>,
+{
+    let vals = args
+        .iter()
+        .map(from_lispval)
+        .collect::<Result<Vec<A>, LispErr>>()?;
+    Ok(Bool(vals.windows(2).all(|pair| f(&pair[0], &pair[1]))))
+}
+
 // According to the r5rs spec, monoidal numeric functions return the identity element if they're invoked with 0 args
 // e.g. (+) evaluates to 0, and (*) evaluates to 1
-pub fn monoidal_numeric_op<F>(f: F, init: i32, args: &[LispVal]) -> Result<LispVal, LispErr>
+pub fn monoidal_numeric_op<F>(f: F, init: BigInt, args: &[LispVal]) -> Result<LispVal, LispErr>
 where
-    F: Fn(i32, i32) -> i32,
+    F: Fn(BigInt, BigInt) -> BigInt,
 {
     let res = args.iter().fold(Ok(init), |acc, arg| {
         acc.and_then(|x| arg.integer().map(|y| f(x, y)))
@@ -0,0 +1,102 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::eval::apply;
+use crate::parameters;
+use crate::ports::{self, InputPort, LispPort, OutputPort};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::rc::Rc;
+
+fn file_error(e: io::Error, path: &LispVal) -> LispErr {
+    FileError(e.to_string(), path.clone())
+}
+
+fn open_input(path: &LispVal, binary: bool) -> Result<LispVal, LispErr> {
+    let file = File::open(path.string()?.as_str()).map_err(|e| file_error(e, path))?;
+    let reader = BufReader::new(file);
+    Ok(Port(Rc::new(LispPort::file(
+        path,
+        binary,
+        InputPort(reader),
+    )?)))
+}
+
+// Creates the file, or empties it if it already exists.
+fn open_output(path: &LispVal, binary: bool) -> Result<LispVal, LispErr> {
+    let file = File::create(path.string()?.as_str()).map_err(|e| file_error(e, path))?;
+    let writer = BufWriter::new(file);
+    Ok(Port(Rc::new(LispPort::file(
+        path,
+        binary,
+        OutputPort(writer),
+    )?)))
+}
+
+pub fn open_input_file(path: &LispVal) -> Result<LispVal, LispErr> {
+    open_input(path, false)
+}
+
+pub fn open_binary_input_file(path: &LispVal) -> Result<LispVal, LispErr> {
+    open_input(path, true)
+}
+
+pub fn open_output_file(path: &LispVal) -> Result<LispVal, LispErr> {
+    open_output(path, false)
+}
+
+pub fn open_binary_output_file(path: &LispVal) -> Result<LispVal, LispErr> {
+    open_output(path, true)
+}
+
+// Calls `f` with the port, then closes it, even if `f` fails.
+fn with_port<F>(port: LispVal, f: F) -> Result<LispVal, LispErr>
+where
+    F: FnOnce(&LispVal) -> Result<LispVal, LispErr>,
+{
+    let result = f(&port);
+    let closed = ports::close_port(&port);
+    let val = result?;
+    closed?;
+    Ok(val)
+}
+
+// (call-with-input-file path proc) calls proc with a port reading the file.
+pub fn call_with_input_file(path: &LispVal, proc: &LispVal) -> Result<LispVal, LispErr> {
+    with_port(open_input_file(path)?, |port| {
+        apply(proc, std::slice::from_ref(port))
+    })
+}
+
+pub fn call_with_output_file(path: &LispVal, proc: &LispVal) -> Result<LispVal, LispErr> {
+    with_port(open_output_file(path)?, |port| {
+        apply(proc, std::slice::from_ref(port))
+    })
+}
+
+// (with-input-from-file path thunk) calls the thunk with the current input port reading the file.
+pub fn with_input_from_file(path: &LispVal, thunk: &LispVal) -> Result<LispVal, LispErr> {
+    with_port(open_input_file(path)?, |port| {
+        parameters::parameterize(&[(ports::current_input_port(), port.clone())], || {
+            apply(thunk, &[])
+        })
+    })
+}
+
+pub fn with_output_to_file(path: &LispVal, thunk: &LispVal) -> Result<LispVal, LispErr> {
+    with_port(open_output_file(path)?, |port| {
+        parameters::parameterize(&[(ports::current_output_port(), port.clone())], || {
+            apply(thunk, &[])
+        })
+    })
+}
+
+pub fn file_exists(path: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(Path::new(path.string()?.as_str()).exists()))
+}
+
+pub fn delete_file(path: &LispVal) -> Result<LispVal, LispErr> {
+    fs::remove_file(path.string()?.as_str()).map_err(|e| file_error(e, path))?;
+    Ok(Unspecified)
+}
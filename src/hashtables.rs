@@ -0,0 +1,280 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::eval::apply;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ptr;
+use std::rc::Rc;
+
+// How a table compares its keys, picked by the equivalence procedure given to make-hash-table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Equivalence {
+    Equal,
+    Eqv,
+    String,
+}
+
+// A key remembers which equivalence its table uses, so it can hash and compare itself to match.
+// Every key in a table has the same equivalence.
+#[derive(Clone, Debug)]
+struct Key {
+    equivalence: Equivalence,
+    val: LispVal,
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        match self.equivalence {
+            Equivalence::Eqv => self.val.is_eqv(&other.val),
+            Equivalence::Equal | Equivalence::String => self.val == other.val,
+        }
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match (self.equivalence, &self.val) {
+            (Equivalence::Eqv, Str(s)) => s.as_ptr().hash(state),
+            (Equivalence::Eqv, ConsList(cons)) => Rc::as_ptr(cons).hash(state),
+            (Equivalence::Eqv, Vector(vec)) => Rc::as_ptr(vec).hash(state),
+            (Equivalence::Eqv, Bytevector(bytes)) => Rc::as_ptr(bytes).hash(state),
+            (_, val) => val.hash(state),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LispHashTable {
+    equivalence: Equivalence,
+    entries: HashMap<Key, LispVal>,
+}
+
+impl LispHashTable {
+    pub fn new(equivalence: Equivalence) -> LispHashTable {
+        LispHashTable {
+            equivalence,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn key(&self, val: &LispVal) -> Result<Key, LispErr> {
+        if self.equivalence == Equivalence::String {
+            val.string()?;
+        }
+        Ok(Key {
+            equivalence: self.equivalence,
+            val: val.clone(),
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: &LispVal) -> Result<Option<LispVal>, LispErr> {
+        Ok(self.entries.get(&self.key(key)?).cloned())
+    }
+
+    pub fn insert(&mut self, key: &LispVal, val: LispVal) -> Result<(), LispErr> {
+        let key = self.key(key)?;
+        self.entries.insert(key, val);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &LispVal) -> Result<(), LispErr> {
+        let key = self.key(key)?;
+        self.entries.remove(&key);
+        Ok(())
+    }
+
+    // A snapshot of the table's contents, in no particular order
+    pub fn entries(&self) -> Vec<(LispVal, LispVal)> {
+        self.entries
+            .iter()
+            .map(|(key, val)| (key.val.clone(), val.clone()))
+            .collect()
+    }
+}
+
+// Hash tables are only ever equal to themselves.
+impl PartialEq for LispHashTable {
+    fn eq(&self, other: &LispHashTable) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+impl Eq for LispHashTable {}
+
+fn new_hash_table(table: LispHashTable) -> LispVal {
+    HashTable(Rc::new(RefCell::new(table)))
+}
+
+fn equivalence(f: &LispVal) -> Result<Equivalence, LispErr> {
+    match f {
+        PrimitiveFunc(name) if name == "equal?" => Ok(Equivalence::Equal),
+        PrimitiveFunc(name) if name == "eqv?" || name == "eq?" => Ok(Equivalence::Eqv),
+        PrimitiveFunc(name) if name == "string=?" => Ok(Equivalence::String),
+        _ => Err(TypeMismatch(
+            "Expected equal?, eqv?, eq? or string=?".to_string(),
+            f.clone(),
+        )),
+    }
+}
+
+fn missing_key(key: &LispVal) -> LispErr {
+    Default(format!("No value for key {} in the hash table", key))
+}
+
+// (make-hash-table [equivalence [hash]]).  Keys are always hashed to match the equivalence, so a
+// hash function is accepted but ignored.
+pub fn make_hash_table(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let equivalence = match args {
+        [] => Equivalence::Equal,
+        [f] | [f, _] => equivalence(f)?,
+        _ => return Err(NumArgs(2, LispVal::list(args))),
+    };
+    Ok(new_hash_table(LispHashTable::new(equivalence)))
+}
+
+// (hash-table-ref table key [thunk]) calls the thunk if the key is missing, or fails without one.
+pub fn hash_table_ref(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [table, key, rest @ ..] if rest.len() <= 1 => {
+            let found = table.hash_table()?.borrow().get(key)?;
+            match (found, rest.first()) {
+                (Some(val), _) => Ok(val),
+                (None, Some(thunk)) => apply(thunk, &[]),
+                (None, None) => Err(missing_key(key)),
+            }
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+pub fn hash_table_ref_default(
+    table: &LispVal,
+    key: &LispVal,
+    default: &LispVal,
+) -> Result<LispVal, LispErr> {
+    let found = table.hash_table()?.borrow().get(key)?;
+    Ok(found.unwrap_or_else(|| default.clone()))
+}
+
+pub fn hash_table_set(table: &LispVal, key: &LispVal, val: &LispVal) -> Result<LispVal, LispErr> {
+    table.hash_table()?.borrow_mut().insert(key, val.clone())?;
+    Ok(Unspecified)
+}
+
+pub fn hash_table_delete(table: &LispVal, key: &LispVal) -> Result<LispVal, LispErr> {
+    table.hash_table()?.borrow_mut().remove(key)?;
+    Ok(Unspecified)
+}
+
+pub fn hash_table_contains(table: &LispVal, key: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(table.hash_table()?.borrow().get(key)?.is_some()))
+}
+
+// (hash-table-update! table key proc [thunk]) replaces the key's value with the result of calling
+// `proc` on it, calling the thunk for the old value if the key is missing.  The table isn't
+// borrowed while either runs, so they're free to use it.
+pub fn hash_table_update(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [table, key, f, rest @ ..] if rest.len() <= 1 => {
+            let t = table.hash_table()?;
+            let found = t.borrow().get(key)?;
+            let old = match (found, rest.first()) {
+                (Some(val), _) => val,
+                (None, Some(thunk)) => apply(thunk, &[])?,
+                (None, None) => return Err(missing_key(key)),
+            };
+            let new = apply(f, &[old])?;
+            t.borrow_mut().insert(key, new)?;
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(4, LispVal::list(args))),
+    }
+}
+
+pub fn hash_table_update_default(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [table, key, f, default] => {
+            let t = table.hash_table()?;
+            let old = t.borrow().get(key)?.unwrap_or_else(|| default.clone());
+            let new = apply(f, &[old])?;
+            t.borrow_mut().insert(key, new)?;
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(4, LispVal::list(args))),
+    }
+}
+
+pub fn hash_table_size(table: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number(table.hash_table()?.borrow().size().into()))
+}
+
+pub fn hash_table_keys(table: &LispVal) -> Result<LispVal, LispErr> {
+    let entries = table.hash_table()?.borrow().entries();
+    let keys: Vec<LispVal> = entries.into_iter().map(|(key, _)| key).collect();
+    Ok(LispVal::list(&keys))
+}
+
+pub fn hash_table_values(table: &LispVal) -> Result<LispVal, LispErr> {
+    let entries = table.hash_table()?.borrow().entries();
+    let vals: Vec<LispVal> = entries.into_iter().map(|(_, val)| val).collect();
+    Ok(LispVal::list(&vals))
+}
+
+// Calls `f` with each key and value.  It walks a snapshot, so `f` may modify the table.
+pub fn hash_table_walk(table: &LispVal, f: &LispVal) -> Result<LispVal, LispErr> {
+    let entries = table.hash_table()?.borrow().entries();
+    for (key, val) in entries {
+        apply(f, &[key, val])?;
+    }
+    Ok(Unspecified)
+}
+
+pub fn hash_table_to_alist(table: &LispVal) -> Result<LispVal, LispErr> {
+    let entries = table.hash_table()?.borrow().entries();
+    let pairs: Vec<LispVal> = entries
+        .into_iter()
+        .map(|(key, val)| LispVal::cons(key, val))
+        .collect();
+    Ok(LispVal::list(&pairs))
+}
+
+// (alist->hash-table alist [equivalence [hash]]).  Earlier pairs take precedence over later ones
+// with the same key, as they would for assoc.
+pub fn alist_to_hash_table(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [alist, rest @ ..] if rest.len() <= 2 => {
+            let mut table = LispHashTable::new(match rest.first() {
+                Some(f) => equivalence(f)?,
+                None => Equivalence::Equal,
+            });
+            let pairs: Vec<LispVal> = alist.iter().collect();
+            for pair in pairs.into_iter().rev() {
+                table.insert(&pair.car()?, pair.cdr()?)?;
+            }
+            Ok(new_hash_table(table))
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+pub fn hash_table_copy(table: &LispVal) -> Result<LispVal, LispErr> {
+    let t = table.hash_table()?;
+    let t = t.borrow();
+    Ok(new_hash_table(LispHashTable {
+        equivalence: t.equivalence,
+        entries: t.entries.clone(),
+    }))
+}
+
+pub fn hash_table_clear(table: &LispVal) -> Result<LispVal, LispErr> {
+    table.hash_table()?.borrow_mut().entries.clear();
+    Ok(Unspecified)
+}
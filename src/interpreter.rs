@@ -0,0 +1,69 @@
+use crate::ast::*;
+use crate::eval::{self, Env};
+use crate::parameters;
+use crate::parser::parser_combinator;
+use crate::ports::{self, Port};
+
+// An interpreter for embedding risp: a global environment, plus the ports its code uses as the
+// current input, output and error ports.  Ports that aren't set stay on the console.
+#[derive(Default)]
+pub struct Interpreter {
+    env: Env,
+    input: Option<LispVal>,
+    output: Option<LispVal>,
+    error: Option<LispVal>,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::default()
+    }
+
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    pub fn define(&self, name: &str, val: LispVal) {
+        self.env.define(name.to_string(), val)
+    }
+
+    pub fn set_input_port<P: Port + 'static>(&mut self, port: P) {
+        self.input = Some(ports::new_port("input", false, port));
+    }
+
+    pub fn set_output_port<P: Port + 'static>(&mut self, port: P) {
+        self.output = Some(ports::new_port("output", false, port));
+    }
+
+    pub fn set_error_port<P: Port + 'static>(&mut self, port: P) {
+        self.error = Some(ports::new_port("error", false, port));
+    }
+
+    // Evaluates the expression with this interpreter's ports as the current ports.  Output is
+    // flushed afterwards, whether or not evaluation succeeded.
+    pub fn eval(&self, expr: &LispVal) -> Result<LispVal, LispErr> {
+        let current = [
+            (ports::current_input_port(), &self.input),
+            (ports::current_output_port(), &self.output),
+            (ports::current_error_port(), &self.error),
+        ];
+        let bindings: Vec<(LispVal, LispVal)> = current
+            .iter()
+            .filter_map(|(param, port)| port.as_ref().map(|port| (param.clone(), port.clone())))
+            .collect();
+        let result = parameters::parameterize(&bindings, || eval::eval(&self.env, expr));
+        let flushed: Result<Vec<LispVal>, LispErr> = [&self.output, &self.error]
+            .iter()
+            .copied()
+            .flatten()
+            .map(|port| ports::flush_output_port(std::slice::from_ref(port)))
+            .collect();
+        let val = result?;
+        flushed?;
+        Ok(val)
+    }
+
+    pub fn eval_str(&self, source: &str) -> Result<LispVal, LispErr> {
+        self.eval(&parser_combinator::scheme(source)?)
+    }
+}
@@ -1,5 +1,19 @@
 #![feature(trace_macros)]
 
 pub mod ast;
+pub mod bitwise;
+pub mod bytevectors;
+pub mod chars;
 pub mod eval;
+pub mod files;
+pub mod hashtables;
+pub mod interpreter;
+pub mod lists;
+pub mod parameters;
 pub mod parser;
+pub mod ports;
+pub mod promises;
+pub mod records;
+pub mod streams;
+pub mod strings;
+pub mod vectors;
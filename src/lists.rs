@@ -0,0 +1,382 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::eval::apply;
+use num_bigint::BigInt;
+
+// Anything but #f counts as true to the procedures that take predicates.
+fn is_true(val: &LispVal) -> bool {
+    !matches!(val, Bool(false))
+}
+
+// Compares with the equivalence procedure given as an optional argument, or with `equal?`.
+fn equivalent(f: Option<&LispVal>, x: &LispVal, y: &LispVal) -> Result<bool, LispErr> {
+    match f {
+        Some(f) => Ok(is_true(&apply(f, &[x.clone(), y.clone()])?)),
+        None => Ok(x == y),
+    }
+}
+
+// The arguments for each call when a procedure is mapped over several lists: the first elements of
+// every list, then the second, and so on, stopping at the end of the shortest list.
+fn zip_lists(lists: &[LispVal]) -> Result<Vec<Vec<LispVal>>, LispErr> {
+    let lists = lists
+        .iter()
+        .map(LispVal::list_items)
+        .collect::<Result<Vec<Vec<LispVal>>, LispErr>>()?;
+    let len = lists.iter().map(Vec::len).min().unwrap_or(0);
+    Ok((0..len)
+        .map(|i| lists.iter().map(|list| list[i].clone()).collect())
+        .collect())
+}
+
+fn out_of_range(k: &LispVal) -> LispErr {
+    OutOfRange("List index is out of range".to_string(), k.clone())
+}
+
+pub fn list(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    Ok(LispVal::list(args))
+}
+
+pub fn length(list: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number(list.list_items()?.len().into()))
+}
+
+// Every list but the last is copied; the result shares the last one, which needn't be a list.
+pub fn append(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [] => Ok(Nil),
+        [lists @ .., last] => {
+            let mut items = Vec::new();
+            for list in lists {
+                items.extend(list.list_items()?);
+            }
+            Ok(items
+                .into_iter()
+                .rev()
+                .fold(last.clone(), |tail, item| LispVal::cons(item, tail)))
+        }
+    }
+}
+
+pub fn reverse(list: &LispVal) -> Result<LispVal, LispErr> {
+    let mut items = list.list_items()?;
+    items.reverse();
+    Ok(LispVal::list(&items))
+}
+
+pub fn list_tail(list: &LispVal, k: &LispVal) -> Result<LispVal, LispErr> {
+    let mut tail = list.clone();
+    for _ in 0..k.index()? {
+        tail = match tail {
+            ConsList(cons) => cons.cdr(),
+            _ => return Err(out_of_range(k)),
+        };
+    }
+    Ok(tail)
+}
+
+pub fn list_ref(list: &LispVal, k: &LispVal) -> Result<LispVal, LispErr> {
+    match list_tail(list, k)? {
+        ConsList(cons) => Ok(cons.car()),
+        _ => Err(out_of_range(k)),
+    }
+}
+
+pub fn take(list: &LispVal, k: &LispVal) -> Result<LispVal, LispErr> {
+    let mut items = Vec::new();
+    let mut tail = list.clone();
+    for _ in 0..k.index()? {
+        tail = match tail {
+            ConsList(cons) => {
+                items.push(cons.car());
+                cons.cdr()
+            }
+            _ => return Err(out_of_range(k)),
+        };
+    }
+    Ok(LispVal::list(&items))
+}
+
+pub fn last_pair(list: &LispVal) -> Result<LispVal, LispErr> {
+    match list_shape(list) {
+        (0, _) => Err(TypeMismatch("Expected a pair".to_string(), list.clone())),
+        (len, Some(_)) => list_tail(list, &Number((len - 1).into())),
+        (_, None) => Err(TypeMismatch(
+            "Expected a finite list".to_string(),
+            list.clone(),
+        )),
+    }
+}
+
+pub fn map(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [f, lists @ ..] if !lists.is_empty() => {
+            let results = zip_lists(lists)?
+                .iter()
+                .map(|items| apply(f, items))
+                .collect::<Result<Vec<LispVal>, LispErr>>()?;
+            Ok(LispVal::list(&results))
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+pub fn for_each(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [f, lists @ ..] if !lists.is_empty() => {
+            for items in zip_lists(lists)? {
+                apply(f, &items)?;
+            }
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+fn filter_items(pred: &LispVal, list: &LispVal, keep: bool) -> Result<LispVal, LispErr> {
+    let mut kept = Vec::new();
+    for item in list.list_items()? {
+        if is_true(&apply(pred, std::slice::from_ref(&item))?) == keep {
+            kept.push(item);
+        }
+    }
+    Ok(LispVal::list(&kept))
+}
+
+pub fn filter(pred: &LispVal, list: &LispVal) -> Result<LispVal, LispErr> {
+    filter_items(pred, list, true)
+}
+
+pub fn remove(pred: &LispVal, list: &LispVal) -> Result<LispVal, LispErr> {
+    filter_items(pred, list, false)
+}
+
+// Returns two values: the elements that satisfy the predicate, and those that don't.
+pub fn partition(pred: &LispVal, list: &LispVal) -> Result<LispVal, LispErr> {
+    let (mut kept, mut rejected) = (Vec::new(), Vec::new());
+    for item in list.list_items()? {
+        if is_true(&apply(pred, std::slice::from_ref(&item))?) {
+            kept.push(item);
+        } else {
+            rejected.push(item);
+        }
+    }
+    Ok(Values(vec![LispVal::list(&kept), LispVal::list(&rejected)]))
+}
+
+// (fold kons knil list ...) calls (kons elem ... acc) from the left.
+pub fn fold(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [f, init, lists @ ..] if !lists.is_empty() => {
+            zip_lists(lists)?
+                .into_iter()
+                .try_fold(init.clone(), |acc, mut items| {
+                    items.push(acc);
+                    apply(f, &items)
+                })
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// (fold-left f init list ...) calls (f acc elem ...) from the left.
+pub fn fold_left(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [f, init, lists @ ..] if !lists.is_empty() => {
+            zip_lists(lists)?
+                .into_iter()
+                .try_fold(init.clone(), |acc, mut items| {
+                    items.insert(0, acc);
+                    apply(f, &items)
+                })
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// (fold-right f init list ...) calls (f elem ... acc) from the right.
+pub fn fold_right(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [f, init, lists @ ..] if !lists.is_empty() => {
+            zip_lists(lists)?
+                .into_iter()
+                .rev()
+                .try_fold(init.clone(), |acc, mut items| {
+                    items.push(acc);
+                    apply(f, &items)
+                })
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// (reduce f ridentity list) folds f over the list starting from its first element, or returns
+// ridentity if the list is empty.
+pub fn reduce(f: &LispVal, ridentity: &LispVal, list: &LispVal) -> Result<LispVal, LispErr> {
+    let mut items = list.list_items()?.into_iter();
+    match items.next() {
+        Some(first) => items.try_fold(first, |acc, item| apply(f, &[item, acc])),
+        None => Ok(ridentity.clone()),
+    }
+}
+
+pub fn delete(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [x, list, rest @ ..] if rest.len() <= 1 => {
+            let mut kept = Vec::new();
+            for item in list.list_items()? {
+                if !equivalent(rest.first(), x, &item)? {
+                    kept.push(item);
+                }
+            }
+            Ok(LispVal::list(&kept))
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+pub fn delete_duplicates(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [list, rest @ ..] if rest.len() <= 1 => {
+            let mut kept: Vec<LispVal> = Vec::new();
+            for item in list.list_items()? {
+                let mut seen = false;
+                for k in kept.iter() {
+                    if equivalent(rest.first(), k, &item)? {
+                        seen = true;
+                        break;
+                    }
+                }
+                if !seen {
+                    kept.push(item);
+                }
+            }
+            Ok(LispVal::list(&kept))
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+// Returns the first tail of `list` whose car matches `x`, or #f.
+fn find_tail<F>(matches: F, x: &LispVal, list: &LispVal) -> Result<LispVal, LispErr>
+where
+    F: Fn(&LispVal, &LispVal) -> Result<bool, LispErr>,
+{
+    let mut tail = list.clone();
+    for item in list.list_items()? {
+        if matches(x, &item)? {
+            return Ok(tail);
+        }
+        tail = tail.cdr()?;
+    }
+    Ok(Bool(false))
+}
+
+pub fn memq(x: &LispVal, list: &LispVal) -> Result<LispVal, LispErr> {
+    find_tail(|x, y| Ok(x.is_eq(y)), x, list)
+}
+
+pub fn memv(x: &LispVal, list: &LispVal) -> Result<LispVal, LispErr> {
+    find_tail(|x, y| Ok(x.is_eqv(y)), x, list)
+}
+
+pub fn member(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [x, list, rest @ ..] if rest.len() <= 1 => {
+            find_tail(|x, y| equivalent(rest.first(), x, y), x, list)
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// Returns the first pair in an association list whose car matches `x`, or #f.
+fn find_pair<F>(matches: F, x: &LispVal, alist: &LispVal) -> Result<LispVal, LispErr>
+where
+    F: Fn(&LispVal, &LispVal) -> Result<bool, LispErr>,
+{
+    for pair in alist.list_items()? {
+        if matches(x, &pair.car()?)? {
+            return Ok(pair);
+        }
+    }
+    Ok(Bool(false))
+}
+
+pub fn assq(x: &LispVal, alist: &LispVal) -> Result<LispVal, LispErr> {
+    find_pair(|x, y| Ok(x.is_eq(y)), x, alist)
+}
+
+pub fn assv(x: &LispVal, alist: &LispVal) -> Result<LispVal, LispErr> {
+    find_pair(|x, y| Ok(x.is_eqv(y)), x, alist)
+}
+
+pub fn assoc(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [x, alist, rest @ ..] if rest.len() <= 1 => {
+            find_pair(|x, y| equivalent(rest.first(), x, y), x, alist)
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// (iota count [start [step]])
+pub fn iota(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [count, rest @ ..] if rest.len() <= 2 => {
+            let count = count.index()?;
+            let mut n = match rest.first() {
+                Some(start) => start.integer()?,
+                None => 0.into(),
+            };
+            let step = match rest.get(1) {
+                Some(step) => step.integer()?,
+                None => 1.into(),
+            };
+            // cons the list up from its last element rather than collecting a Vec first, since
+            // reserving space for a huge count up front would abort the process
+            n += &step * BigInt::from(count);
+            let mut list = Nil;
+            for _ in 0..count {
+                n -= &step;
+                list = LispVal::cons(Number(n.clone()), list);
+            }
+            Ok(list)
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+// Returns the first true result of calling `pred` on the lists' elements, or #f.
+pub fn any(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [pred, lists @ ..] if !lists.is_empty() => {
+            for items in zip_lists(lists)? {
+                let result = apply(pred, &items)?;
+                if is_true(&result) {
+                    return Ok(result);
+                }
+            }
+            Ok(Bool(false))
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+// Returns #f if `pred` is false for any of the lists' elements, or else its last result (#t if
+// the lists are empty).
+pub fn every(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [pred, lists @ ..] if !lists.is_empty() => {
+            let mut result = Bool(true);
+            for items in zip_lists(lists)? {
+                result = apply(pred, &items)?;
+                if !is_true(&result) {
+                    return Ok(result);
+                }
+            }
+            Ok(result)
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
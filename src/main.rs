@@ -1,8 +1,6 @@
-mod ast;
-mod eval;
-mod parser;
-
-use std::collections::HashMap;
+use risp::ast::LispVal;
+use risp::interpreter::Interpreter;
+use std::io::{self, Write};
 
 fn main() {
     repl();
@@ -10,21 +8,31 @@ fn main() {
 
 fn repl() {
     let mut rl = rustyline::Editor::<()>::new();
-    let mut env = HashMap::new();
+    let interpreter = Interpreter::new();
     loop {
         match rl.readline("risp λ  ") {
             Ok(s) => {
                 rl.add_history_entry(s.as_str());
                 match s.as_str() {
                     ":q" | "quit" | "exit" => break,
-                    ",print_env" => println!(" {:?}", env),
+                    ",print_env" => {
+                        for (var, val) in interpreter.env().bindings() {
+                            println!(" {}: {}", var, val)
+                        }
+                    }
                     input => {
-                        match parser::parser_combinator::scheme(input)
-                            .and_then(|ast| eval::eval(&mut env, &ast))
-                        {
+                        match interpreter.eval_str(input) {
+                            Ok(LispVal::Values(vals)) => {
+                                for val in vals {
+                                    println!("  {}", val)
+                                }
+                            }
+                            Ok(LispVal::Unspecified) => {}
                             Ok(res) => println!("  {}", res),
                             Err(e) => println!("Error: {:?}", e),
                         }
+                        // show anything written without a newline before the next prompt
+                        let _ = io::stdout().flush();
                     }
                 }
             }
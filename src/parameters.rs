@@ -0,0 +1,76 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::eval::apply;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// A parameter object holds a dynamically scoped value: calling it with no arguments returns the
+// value, and parameterize rebinds it for the extent of its body.
+#[derive(Debug)]
+pub struct LispParameter {
+    pub value: RefCell<LispVal>,
+    // Applied to the initial value, and to every value given in a parameterize
+    pub converter: Option<LispVal>,
+}
+
+impl LispParameter {
+    fn convert(&self, val: &LispVal) -> Result<LispVal, LispErr> {
+        match &self.converter {
+            Some(converter) => apply(converter, std::slice::from_ref(val)),
+            None => Ok(val.clone()),
+        }
+    }
+}
+
+// (make-parameter value [converter])
+pub fn make_parameter(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (val, converter) = match args {
+        [val] => (val, None),
+        [val, converter] => (val, Some(converter.clone())),
+        _ => return Err(NumArgs(2, LispVal::list(args))),
+    };
+    let param = LispParameter {
+        value: RefCell::new(Nil),
+        converter,
+    };
+    param.value.replace(param.convert(val)?);
+    Ok(Parameter(Rc::new(param)))
+}
+
+pub fn apply_parameter(param: &LispParameter, args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [] => Ok(param.value.borrow().clone()),
+        _ => Err(NumArgs(0, LispVal::list(args))),
+    }
+}
+
+// Gives each parameter its new value (after converting them all) while `body` runs, then
+// restores the old values whether or not the body returned an error.  They're restored in reverse,
+// so a parameter bound twice gets back the value it had before the first binding.
+pub fn parameterize<F>(bindings: &[(LispVal, LispVal)], body: F) -> Result<LispVal, LispErr>
+where
+    F: FnOnce() -> Result<LispVal, LispErr>,
+{
+    let mut params = Vec::new();
+    for (param, val) in bindings {
+        match param {
+            Parameter(param) => params.push((param.clone(), param.convert(val)?)),
+            _ => {
+                return Err(TypeMismatch(
+                    "Expected a parameter".to_string(),
+                    param.clone(),
+                ))
+            }
+        }
+    }
+    let old: Vec<LispVal> = params
+        .iter()
+        .map(|(param, val)| param.value.replace(val.clone()))
+        .collect();
+    let result = body();
+    for ((param, _), val) in params.iter().zip(old).rev() {
+        param.value.replace(val);
+    }
+    result
+}
@@ -3,17 +3,24 @@ pub mod parser_combinator {
     use crate::ast::LispErr;
     use crate::ast::LispErr::ParseError;
     use crate::ast::LispVal;
+    use crate::bytevectors;
+    use crate::chars;
+    use crate::vectors;
     use nom::{
         branch::alt,
-        bytes::complete::{escaped, tag},
-        character::complete::{alpha1, alphanumeric1, digit1, none_of, one_of, space1},
-        combinator::{all_consuming, flat_map, map},
+        bytes::complete::tag,
+        character::complete::{alpha1, alphanumeric1, digit1, multispace0, multispace1, one_of},
+        combinator::{all_consuming, flat_map, map, map_res},
         do_parse,
+        error::ErrorKind,
         multi::{many0, separated_nonempty_list},
         named,
-        sequence::{delimited, separated_pair},
+        sequence::{delimited, pair, preceded, separated_pair, terminated},
+        Err::Error,
         IResult,
     };
+    use num_bigint::BigInt;
+    use num_traits::ToPrimitive;
     use std::str::FromStr;
 
     pub fn boolean(i: &str) -> IResult<&str, LispVal> {
@@ -22,16 +29,96 @@ pub mod parser_combinator {
         alt((t, f))(i)
     }
 
+    // `#\a`, `#\space`, or `#\x41`.  The character after the backslash is taken as-is, so
+    // `#\(` and `#\ ` work, and any letters or digits after it make up a character name.
+    pub fn character(i: &str) -> IResult<&str, LispVal> {
+        let (rest, _) = tag("#\\")(i)?;
+        let first = match rest.chars().next() {
+            Some(c) => c,
+            None => return Err(Error((rest, ErrorKind::Eof))),
+        };
+        let name_len = rest[first.len_utf8()..]
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len() - first.len_utf8())
+            + first.len_utf8();
+        let (name, rest) = rest.split_at(name_len);
+
+        let c = if name.chars().count() == 1 {
+            Some(first)
+        } else if let Some(c) = chars::char_from_name(name) {
+            Some(c)
+        } else if first == 'x' {
+            u32::from_str_radix(&name[1..], 16)
+                .ok()
+                .and_then(std::char::from_u32)
+        } else {
+            None
+        };
+        match c {
+            Some(c) => Ok((rest, LispVal::Char(c))),
+            None => Err(Error((i, ErrorKind::Char))),
+        }
+    }
+
     pub fn string(i: &str) -> IResult<&str, LispVal> {
-        // TODO: for some reason, using escaped results in the tests spinning forever.
-        // This "works", although it doesn't handle escaping characters.
+        let (mut rest, _) = tag("\"")(i)?;
+        let mut string = String::new();
+        loop {
+            let mut chars = rest.chars();
+            match chars.next() {
+                Some('"') => return Ok((chars.as_str(), LispVal::Str(string.into()))),
+                Some('\\') => {
+                    let (r, escaped) = string_escape(chars.as_str())?;
+                    string.extend(escaped);
+                    rest = r;
+                }
+                Some(c) => {
+                    string.push(c);
+                    rest = chars.as_str();
+                }
+                None => return Err(Error((rest, ErrorKind::Eof))),
+            }
+        }
+    }
+
+    // Parses what follows a backslash in a string literal.  A backslash followed by optional
+    // whitespace and a line ending is a line continuation, which produces no character at all.
+    fn string_escape(i: &str) -> IResult<&str, Option<char>> {
+        fn skip_intraline(i: &str) -> &str {
+            i.trim_start_matches(&[' ', '\t'][..])
+        }
 
-        //let contents = escaped(many0(none_of("\"")), '\\', one_of("n\\\"t"));
-        // map(string, |s: &str| LispVal::Str(s.into()))(i)
-        let contents = many0(none_of("\""));
-        let string = delimited(tag("\""), contents, tag("\""));
-        map(string, |s: Vec<char>| LispVal::Str(s.into_iter().collect()))(i)
+        let mut chars = i.chars();
+        let escaped = match chars.next() {
+            Some('a') => '\u{7}',
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some(c @ '"') | Some(c @ '\\') | Some(c @ '|') => c,
+            Some('x') => {
+                let hex = chars.as_str();
+                let end = hex.find(';').ok_or(Error((i, ErrorKind::Escaped)))?;
+                return u32::from_str_radix(&hex[..end], 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                    .map(|c| (&hex[end + 1..], Some(c)))
+                    .ok_or(Error((i, ErrorKind::Escaped)));
+            }
+            _ => {
+                let rest = skip_intraline(i);
+                match rest
+                    .strip_prefix("\r\n")
+                    .or_else(|| rest.strip_prefix('\n'))
+                {
+                    Some(rest) => return Ok((skip_intraline(rest), None)),
+                    None => return Err(Error((i, ErrorKind::Escaped))),
+                }
+            }
+        };
+        Ok((chars.as_str(), Some(escaped)))
     }
+
     // one_of returns a parser of char, while alpha1 returns a parser of &str.
     // to get the types to line up, use one_of to reimplement alpha1 for now.
     pub fn symbol(i: &str) -> IResult<&str, char> {
@@ -62,8 +149,16 @@ pub mod parser_combinator {
         })(i)
     }
 
+    // `#:name`, a keyword, as used to name arguments to procedures made with lambda*
+    pub fn keyword(i: &str) -> IResult<&str, LispVal> {
+        map(preceded(tag("#:"), atom), |name| match name {
+            LispVal::Atom(name) => LispVal::Keyword(name),
+            _ => name,
+        })(i)
+    }
+
     pub fn number(i: &str) -> IResult<&str, LispVal> {
-        map(digit1, |s| LispVal::Number(i32::from_str(s).unwrap()))(i)
+        map(digit1, |s| LispVal::Number(BigInt::from_str(s).unwrap()))(i)
     }
 
     pub fn list(i: &str) -> IResult<&str, LispVal> {
@@ -75,15 +170,45 @@ pub mod parser_combinator {
             list
         }
 
-        let list_contents = map(separated_nonempty_list(space1, expr), |exprs| {
+        let list_contents = map(separated_nonempty_list(multispace1, expr), |exprs| {
             to_list(exprs)
         });
 
-        let empty = map(tag("()"), |_| LispVal::Nil);
-        let non_empty = delimited(tag("("), list_contents, tag(")"));
+        let empty = map(pair(open_paren, tag(")")), |_| LispVal::Nil);
+        let non_empty = delimited(open_paren, list_contents, close_paren);
         alt((empty, non_empty))(i)
     }
 
+    // Items in a list can be separated by any whitespace, including newlines, and there can be
+    // whitespace just inside the parentheses.
+    fn open_paren(i: &str) -> IResult<&str, &str> {
+        terminated(tag("("), multispace0)(i)
+    }
+
+    fn close_paren(i: &str) -> IResult<&str, &str> {
+        preceded(multispace0, tag(")"))(i)
+    }
+
+    pub fn vector(i: &str) -> IResult<&str, LispVal> {
+        map(preceded(tag("#"), list), |l| {
+            vectors::new_vector(l.iter().collect())
+        })(i)
+    }
+
+    pub fn bytevector(i: &str) -> IResult<&str, LispVal> {
+        fn to_bytes(list: LispVal) -> Result<Vec<u8>, ()> {
+            list.iter()
+                .map(|b| match b {
+                    LispVal::Number(n) => n.to_u8().ok_or(()),
+                    _ => Err(()),
+                })
+                .collect()
+        }
+        map(map_res(preceded(tag("#u8"), list), to_bytes), |bytes| {
+            bytevectors::new_bytevector(bytes)
+        })(i)
+    }
+
     pub fn quoted(i: &str) -> IResult<&str, LispVal> {
         flat_map(tag("\'"), |_| {
             map(expr, |e| {
@@ -114,17 +239,30 @@ pub mod parser_combinator {
           })})})})})(i)
         */
 
-        let contents = flat_map(separated_nonempty_list(space1, expr), |first| {
-            map(delimited(tag(" . "), expr, tag("")), move |end| {
-                to_list(&first, end)
-            })
+        let contents = flat_map(separated_nonempty_list(multispace1, expr), |first| {
+            map(
+                preceded(delimited(multispace1, tag("."), multispace1), expr),
+                move |end| to_list(&first, end),
+            )
         });
 
-        delimited(tag("("), contents, tag(")"))(i)
+        delimited(open_paren, contents, close_paren)(i)
     }
 
     pub fn expr(i: &str) -> IResult<&str, LispVal> {
-        alt((boolean, atom, number, string, dotted_list, list, quoted))(i)
+        alt((
+            boolean,
+            character,
+            vector,
+            bytevector,
+            keyword,
+            atom,
+            number,
+            string,
+            dotted_list,
+            list,
+            quoted,
+        ))(i)
     }
 
     pub fn scheme(i: &str) -> Result<LispVal, LispErr> {
@@ -0,0 +1,814 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::bytevectors;
+use crate::eval::apply;
+use crate::parameters::{self, LispParameter};
+use crate::parser::parser_combinator;
+use crate::strings;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+
+// Something Scheme code can read from, write to, or both, implemented in Rust.  Any reader or
+// writer can be one: wrap it in InputPort or OutputPort, or something that's both (like a
+// TcpStream) in InputOutputPort.
+pub trait Port {
+    fn reader(&mut self) -> Option<&mut dyn Read> {
+        None
+    }
+
+    fn writer(&mut self) -> Option<&mut dyn Write> {
+        None
+    }
+
+    // Whether reading may block waiting for input, like reading from the console
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+pub struct InputPort<R>(pub R);
+
+impl<R: Read> Port for InputPort<R> {
+    fn reader(&mut self) -> Option<&mut dyn Read> {
+        Some(&mut self.0)
+    }
+}
+
+pub struct OutputPort<W>(pub W);
+
+impl<W: Write> Port for OutputPort<W> {
+    fn writer(&mut self) -> Option<&mut dyn Write> {
+        Some(&mut self.0)
+    }
+}
+
+pub struct InputOutputPort<T>(pub T);
+
+impl<T: Read + Write> Port for InputOutputPort<T> {
+    fn reader(&mut self) -> Option<&mut dyn Read> {
+        Some(&mut self.0)
+    }
+
+    fn writer(&mut self) -> Option<&mut dyn Write> {
+        Some(&mut self.0)
+    }
+}
+
+// An output port's destination that keeps everything written to it in memory.  Clones share the
+// same buffer, so Rust code can hand one to an interpreter and look at the output afterwards.
+#[derive(Clone, Debug, Default)]
+pub struct OutputBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn new() -> OutputBuffer {
+        OutputBuffer::default()
+    }
+
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
+
+    // The contents as text, with any invalid UTF-8 replaced by U+FFFD
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Port for OutputBuffer {
+    fn writer(&mut self) -> Option<&mut dyn Write> {
+        Some(self)
+    }
+}
+
+// The Scheme side of a port.  Textual ports read and write characters as UTF-8; binary ports read
+// and write bytes.
+pub struct LispPort {
+    pub name: String,
+    pub binary: bool,
+    port: RefCell<Box<dyn Port>>,
+    input: bool,
+    output: bool,
+    // Bytes read but not yet consumed, so characters can be peeked at and decoded
+    buffer: RefCell<VecDeque<u8>>,
+    // What a port made by output_buffer has written, for get-output-string
+    captured: Option<OutputBuffer>,
+    // The path of a file port, whose I/O errors are file errors
+    file: Option<LispVal>,
+    open: Cell<bool>,
+}
+
+impl fmt::Debug for LispPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LispPort")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+struct Input<'a> {
+    reader: &'a mut dyn Read,
+    buffer: &'a mut VecDeque<u8>,
+    interactive: bool,
+}
+
+// The length of the UTF-8 sequence a byte starts
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+impl Input<'_> {
+    // Buffers at least `n` bytes, unless the input ends first.
+    fn fill(&mut self, n: usize) -> io::Result<()> {
+        let mut chunk = [0; 4096];
+        while self.buffer.len() < n {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => self.buffer.extend(&chunk[..read]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn peek_byte(&mut self) -> io::Result<Option<u8>> {
+        self.fill(1)?;
+        Ok(self.buffer.front().copied())
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        self.fill(1)?;
+        Ok(self.buffer.pop_front())
+    }
+
+    // Decodes the next character and its length in bytes without consuming it.  Invalid UTF-8
+    // reads as U+FFFD, one byte at a time.
+    fn peek_char(&mut self) -> io::Result<Option<(char, usize)>> {
+        let len = match self.peek_byte()? {
+            Some(byte) => utf8_len(byte),
+            None => return Ok(None),
+        };
+        self.fill(len)?;
+        let bytes: Vec<u8> = self.buffer.iter().take(len).copied().collect();
+        match std::str::from_utf8(&bytes) {
+            Ok(s) => Ok(s.chars().next().map(|c| (c, len))),
+            Err(_) => Ok(Some(('\u{fffd}', 1))),
+        }
+    }
+
+    fn read_char(&mut self) -> io::Result<Option<char>> {
+        let next = self.peek_char()?;
+        if let Some((_, len)) = next {
+            self.buffer.drain(..len);
+        }
+        Ok(next.map(|(c, _)| c))
+    }
+
+    fn peek(&mut self) -> io::Result<Option<char>> {
+        Ok(self.peek_char()?.map(|(c, _)| c))
+    }
+
+    // Whether a #| ... |# or #; comment starts here
+    fn at_hash_comment(&mut self) -> io::Result<bool> {
+        self.fill(2)?;
+        Ok(self.buffer.front() == Some(&b'#') && matches!(self.buffer.get(1), Some(b'|' | b';')))
+    }
+
+    // Skips whitespace and comments, up to the next datum or the end of the input.
+    fn skip_atmosphere(&mut self) -> io::Result<()> {
+        while let Some(c) = self.peek()? {
+            if c == ';' {
+                self.skip_comment()?;
+            } else if c.is_whitespace() {
+                self.read_char()?;
+            } else if c == '#' && self.at_hash_comment()? {
+                self.read_char()?;
+                if self.read_char()? == Some('|') {
+                    self.skip_block_comment()?;
+                } else {
+                    // #; comments out the datum after it
+                    self.read_datum()?;
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_comment(&mut self) -> io::Result<()> {
+        while let Some(c) = self.read_char()? {
+            if c == '\n' {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Skips the rest of a #| ... |# comment, which can have others nested inside it.
+    fn skip_block_comment(&mut self) -> io::Result<()> {
+        let mut depth = 1;
+        let mut prev = None;
+        while let Some(c) = self.read_char()? {
+            match (prev, c) {
+                (Some('|'), '#') => depth -= 1,
+                (Some('#'), '|') => depth += 1,
+                _ => {
+                    prev = Some(c);
+                    continue;
+                }
+            }
+            if depth == 0 {
+                break;
+            }
+            // the two characters of a marker don't start another one
+            prev = None;
+        }
+        Ok(())
+    }
+
+    // Reads the rest of a string literal, up to and including its closing quote.
+    fn read_string_literal(&mut self, text: &mut String) -> io::Result<()> {
+        while let Some(c) = self.read_char()? {
+            text.push(c);
+            match c {
+                '"' => break,
+                '\\' => text.extend(self.read_char()?),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Whether an atom ends here.  A quote, or the `#` or `#u8` starting a vector or bytevector,
+    // still needs what follows it.
+    fn at_delimiter(&mut self, text: &str) -> io::Result<bool> {
+        if let "" | "#" | "#u8" = text.trim_start_matches('\'') {
+            return Ok(false);
+        }
+        Ok(match self.peek()? {
+            Some('#') => self.at_hash_comment()?,
+            Some(c) => c.is_whitespace() || "()\";".contains(c),
+            None => true,
+        })
+    }
+
+    // Reads the text of the next datum for the parser, or None at the end of the input.  Comments
+    // inside it become spaces.  A datum cut short by the end of the input is returned as it is,
+    // for the parser to reject.
+    fn read_datum(&mut self) -> io::Result<Option<String>> {
+        self.skip_atmosphere()?;
+        if self.peek()?.is_none() {
+            return Ok(None);
+        }
+        let mut text = String::new();
+        let mut depth = 0;
+        while let Some(c) = self.peek()? {
+            if c == ';' || (c == '#' && self.at_hash_comment()?) {
+                self.skip_atmosphere()?;
+                text.push(' ');
+                continue;
+            }
+            self.read_char()?;
+            text.push(c);
+            let closed = match c {
+                '"' => {
+                    self.read_string_literal(&mut text)?;
+                    depth == 0
+                }
+                '(' => {
+                    depth += 1;
+                    false
+                }
+                ')' => {
+                    depth -= 1;
+                    depth <= 0
+                }
+                // the character after `#\` is taken as it is, even if it's a parenthesis
+                '#' if self.peek()? == Some('\\') => {
+                    text.extend(self.read_char()?);
+                    text.extend(self.read_char()?);
+                    false
+                }
+                _ => false,
+            };
+            if closed || (depth == 0 && self.at_delimiter(&text)?) {
+                break;
+            }
+        }
+        Ok(Some(text))
+    }
+}
+
+impl LispPort {
+    pub fn new<P: Port + 'static>(name: &str, binary: bool, port: P) -> LispPort {
+        LispPort::from_parts(name, binary, Box::new(port), None, None)
+    }
+
+    // A port reading or writing the file at `path`
+    pub fn file<P: Port + 'static>(
+        path: &LispVal,
+        binary: bool,
+        port: P,
+    ) -> Result<LispPort, LispErr> {
+        let name = path.string()?.to_string();
+        Ok(LispPort::from_parts(
+            &name,
+            binary,
+            Box::new(port),
+            None,
+            Some(path.clone()),
+        ))
+    }
+
+    // An output port that collects what's written to it in memory
+    pub fn output_buffer(name: &str, binary: bool) -> LispPort {
+        let buffer = OutputBuffer::new();
+        LispPort::from_parts(name, binary, Box::new(buffer.clone()), Some(buffer), None)
+    }
+
+    fn from_parts(
+        name: &str,
+        binary: bool,
+        mut port: Box<dyn Port>,
+        captured: Option<OutputBuffer>,
+        file: Option<LispVal>,
+    ) -> LispPort {
+        LispPort {
+            name: name.to_string(),
+            binary,
+            input: port.reader().is_some(),
+            output: port.writer().is_some(),
+            port: RefCell::new(port),
+            buffer: RefCell::new(VecDeque::new()),
+            captured,
+            file,
+            open: Cell::new(true),
+        }
+    }
+
+    pub fn is_input(&self) -> bool {
+        self.input
+    }
+
+    pub fn is_output(&self) -> bool {
+        self.output
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.get()
+    }
+
+    fn io_error(&self, e: io::Error) -> LispErr {
+        match &self.file {
+            Some(path) => FileError(e.to_string(), path.clone()),
+            None => Default(format!("I/O error: {}", e)),
+        }
+    }
+
+    fn closed(&self) -> LispErr {
+        Default(format!("Port {} is closed", self.name))
+    }
+
+    fn with_input<T, F>(&self, f: F) -> Result<T, LispErr>
+    where
+        F: FnOnce(&mut Input) -> io::Result<T>,
+    {
+        let mut port = self.port.borrow_mut();
+        let interactive = port.is_interactive();
+        match port.reader() {
+            Some(reader) if self.is_open() => f(&mut Input {
+                reader,
+                buffer: &mut self.buffer.borrow_mut(),
+                interactive,
+            })
+            .map_err(|e| self.io_error(e)),
+            _ => Err(self.closed()),
+        }
+    }
+
+    fn with_writer<F>(&self, f: F) -> Result<(), LispErr>
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()>,
+    {
+        match self.port.borrow_mut().writer() {
+            Some(writer) if self.is_open() => f(writer).map_err(|e| self.io_error(e)),
+            _ => Err(self.closed()),
+        }
+    }
+
+    // Everything written so far to a port made by output_buffer
+    pub fn contents(&self) -> Option<Vec<u8>> {
+        self.captured.as_ref().map(OutputBuffer::contents)
+    }
+
+    pub fn read_char(&self) -> Result<Option<char>, LispErr> {
+        self.with_input(|input| input.read_char())
+    }
+
+    pub fn peek_char(&self) -> Result<Option<char>, LispErr> {
+        self.with_input(|input| Ok(input.peek_char()?.map(|(c, _)| c)))
+    }
+
+    // Reads up to the end of the line, which can end in \n, \r or \r\n, or None at the end of
+    // the input.
+    pub fn read_line(&self) -> Result<Option<String>, LispErr> {
+        self.with_input(|input| {
+            let mut line = String::new();
+            loop {
+                match input.read_char()? {
+                    Some('\n') => return Ok(Some(line)),
+                    Some('\r') => {
+                        if let Some(('\n', _)) = input.peek_char()? {
+                            input.read_char()?;
+                        }
+                        return Ok(Some(line));
+                    }
+                    Some(c) => line.push(c),
+                    None if line.is_empty() => return Ok(None),
+                    None => return Ok(Some(line)),
+                }
+            }
+        })
+    }
+
+    // Reads up to `k` characters, or None at the end of the input.
+    pub fn read_string(&self, k: usize) -> Result<Option<String>, LispErr> {
+        self.with_input(|input| {
+            let mut s = String::new();
+            for _ in 0..k {
+                match input.read_char()? {
+                    Some(c) => s.push(c),
+                    None if s.is_empty() => return Ok(None),
+                    None => break,
+                }
+            }
+            Ok(Some(s))
+        })
+    }
+
+    // Whether reading won't block: anything but an interactive source never does.
+    pub fn is_ready(&self) -> Result<bool, LispErr> {
+        self.with_input(|input| Ok(!input.interactive || !input.buffer.is_empty()))
+    }
+
+    pub fn read_datum(&self) -> Result<Option<String>, LispErr> {
+        self.with_input(|input| input.read_datum())
+    }
+
+    pub fn read_u8(&self) -> Result<Option<u8>, LispErr> {
+        self.with_input(|input| input.read_byte())
+    }
+
+    pub fn peek_u8(&self) -> Result<Option<u8>, LispErr> {
+        self.with_input(|input| input.peek_byte())
+    }
+
+    pub fn write_str(&self, s: &str) -> Result<(), LispErr> {
+        self.with_writer(|output| output.write_all(s.as_bytes()))
+    }
+
+    pub fn write_bytes(&self, bytes: &[u8]) -> Result<(), LispErr> {
+        self.with_writer(|output| output.write_all(bytes))
+    }
+
+    pub fn flush(&self) -> Result<(), LispErr> {
+        self.with_writer(|output| output.flush())
+    }
+
+    // Closing flushes any output; closing a closed port does nothing.
+    pub fn close(&self) -> Result<(), LispErr> {
+        if self.is_open() && self.is_output() {
+            self.flush()?;
+        }
+        self.open.set(false);
+        Ok(())
+    }
+}
+
+// Wraps any port as a Scheme value
+pub fn new_port<P: Port + 'static>(name: &str, binary: bool, port: P) -> LispVal {
+    Port(Rc::new(LispPort::new(name, binary, port)))
+}
+
+struct ConsoleInput(io::Stdin);
+
+impl Port for ConsoleInput {
+    fn reader(&mut self) -> Option<&mut dyn Read> {
+        Some(&mut self.0)
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+fn console_input() -> LispVal {
+    new_port("stdin", false, ConsoleInput(io::stdin()))
+}
+
+fn console_output<W: Write + 'static>(name: &str, writer: W) -> LispVal {
+    new_port(name, false, OutputPort(writer))
+}
+
+fn port_parameter(port: LispVal) -> Rc<LispParameter> {
+    Rc::new(LispParameter {
+        value: RefCell::new(port),
+        converter: None,
+    })
+}
+
+// The current ports are parameter objects, so parameterize can redirect them.
+thread_local! {
+    static CURRENT_INPUT: Rc<LispParameter> = port_parameter(console_input());
+    static CURRENT_OUTPUT: Rc<LispParameter> =
+        port_parameter(console_output("stdout", io::stdout()));
+    static CURRENT_ERROR: Rc<LispParameter> =
+        port_parameter(console_output("stderr", io::stderr()));
+}
+
+pub fn current_input_port() -> LispVal {
+    Parameter(CURRENT_INPUT.with(Rc::clone))
+}
+
+pub fn current_output_port() -> LispVal {
+    Parameter(CURRENT_OUTPUT.with(Rc::clone))
+}
+
+pub fn current_error_port() -> LispVal {
+    Parameter(CURRENT_ERROR.with(Rc::clone))
+}
+
+fn expected_port(kind: &str, val: &LispVal) -> LispErr {
+    TypeMismatch(format!("Expected a {} port", kind), val.clone())
+}
+
+// The port an I/O procedure was given, or the current input port
+fn input_port(port: Option<&LispVal>, binary: bool) -> Result<Rc<LispPort>, LispErr> {
+    let port = port
+        .cloned()
+        .unwrap_or_else(|| CURRENT_INPUT.with(|p| p.value.borrow().clone()));
+    match &port {
+        Port(p) if p.is_input() && p.binary == binary => Ok(p.clone()),
+        _ if binary => Err(expected_port("binary input", &port)),
+        _ => Err(expected_port("textual input", &port)),
+    }
+}
+
+// The port an I/O procedure was given, or the current output port
+fn output_port(port: Option<&LispVal>, binary: bool) -> Result<Rc<LispPort>, LispErr> {
+    let port = port
+        .cloned()
+        .unwrap_or_else(|| CURRENT_OUTPUT.with(|p| p.value.borrow().clone()));
+    match &port {
+        Port(p) if p.is_output() && p.binary == binary => Ok(p.clone()),
+        _ if binary => Err(expected_port("binary output", &port)),
+        _ => Err(expected_port("textual output", &port)),
+    }
+}
+
+fn any_port(val: &LispVal) -> Result<Rc<LispPort>, LispErr> {
+    match val {
+        Port(port) => Ok(port.clone()),
+        _ => Err(TypeMismatch("Expected a port".to_string(), val.clone())),
+    }
+}
+
+// Splits the arguments into the `required` ones and the optional port that follows them.
+fn with_port(args: &[LispVal], required: usize) -> Result<(&[LispVal], Option<&LispVal>), LispErr> {
+    if args.len() == required || args.len() == required + 1 {
+        Ok((&args[..required], args.get(required)))
+    } else {
+        Err(NumArgs(required as i32 + 1, LispVal::list(args)))
+    }
+}
+
+fn char_or_eof(c: Option<char>) -> LispVal {
+    c.map_or(Eof, Char)
+}
+
+fn string_or_eof(s: Option<String>) -> LispVal {
+    s.map_or(Eof, |s| Str(s.into()))
+}
+
+fn byte_or_eof(b: Option<u8>) -> LispVal {
+    b.map_or(Eof, |b| Number(b.into()))
+}
+
+pub fn is_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(matches!(val, Port(_))))
+}
+
+pub fn is_input_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(matches!(val, Port(p) if p.is_input())))
+}
+
+pub fn is_output_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(matches!(val, Port(p) if p.is_output())))
+}
+
+pub fn is_textual_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(matches!(val, Port(p) if !p.binary)))
+}
+
+pub fn is_binary_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(matches!(val, Port(p) if p.binary)))
+}
+
+pub fn is_input_port_open(val: &LispVal) -> Result<LispVal, LispErr> {
+    let port = any_port(val)?;
+    Ok(Bool(port.is_input() && port.is_open()))
+}
+
+pub fn is_output_port_open(val: &LispVal) -> Result<LispVal, LispErr> {
+    let port = any_port(val)?;
+    Ok(Bool(port.is_output() && port.is_open()))
+}
+
+pub fn close_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    any_port(val)?.close()?;
+    Ok(Unspecified)
+}
+
+pub fn close_input_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    match val {
+        Port(port) if port.is_input() => close_port(val),
+        _ => Err(expected_port("input", val)),
+    }
+}
+
+pub fn close_output_port(val: &LispVal) -> Result<LispVal, LispErr> {
+    match val {
+        Port(port) if port.is_output() => close_port(val),
+        _ => Err(expected_port("output", val)),
+    }
+}
+
+pub fn read_char(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    Ok(char_or_eof(input_port(port, false)?.read_char()?))
+}
+
+pub fn peek_char(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    Ok(char_or_eof(input_port(port, false)?.peek_char()?))
+}
+
+pub fn read_line(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    Ok(string_or_eof(input_port(port, false)?.read_line()?))
+}
+
+// (read-string k [port])
+pub fn read_string(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (args, port) = with_port(args, 1)?;
+    let k = args[0].index()?;
+    Ok(string_or_eof(input_port(port, false)?.read_string(k)?))
+}
+
+// (read [port]) parses the next datum from the port.
+pub fn read(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    match input_port(port, false)?.read_datum()? {
+        Some(text) => parser_combinator::scheme(&text),
+        None => Ok(Eof),
+    }
+}
+
+pub fn is_char_ready(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    Ok(Bool(input_port(port, false)?.is_ready()?))
+}
+
+pub fn read_u8(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    Ok(byte_or_eof(input_port(port, true)?.read_u8()?))
+}
+
+pub fn peek_u8(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    Ok(byte_or_eof(input_port(port, true)?.peek_u8()?))
+}
+
+pub fn is_u8_ready(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    Ok(Bool(input_port(port, true)?.is_ready()?))
+}
+
+pub fn display(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (args, port) = with_port(args, 1)?;
+    output_port(port, false)?.write_str(&args[0].display().to_string())?;
+    Ok(Unspecified)
+}
+
+pub fn write(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (args, port) = with_port(args, 1)?;
+    output_port(port, false)?.write_str(&args[0].to_string())?;
+    Ok(Unspecified)
+}
+
+pub fn newline(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    output_port(port, false)?.write_str("\n")?;
+    Ok(Unspecified)
+}
+
+pub fn write_char(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (args, port) = with_port(args, 1)?;
+    let c = args[0].character()?;
+    output_port(port, false)?.write_str(c.encode_utf8(&mut [0; 4]))?;
+    Ok(Unspecified)
+}
+
+// (write-string string [port [start [end]]])
+pub fn write_string(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [s, rest @ ..] if rest.len() <= 3 => {
+            let s = s.string()?;
+            let s = strings::slice(&s, rest.get(1), rest.get(2))?;
+            output_port(rest.first(), false)?.write_str(s)?;
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(4, LispVal::list(args))),
+    }
+}
+
+pub fn write_u8(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (args, port) = with_port(args, 1)?;
+    output_port(port, true)?.write_bytes(&[bytevectors::byte(&args[0])?])?;
+    Ok(Unspecified)
+}
+
+pub fn flush_output_port(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (_, port) = with_port(args, 0)?;
+    let port = match port {
+        Some(port) => any_port(port)?,
+        None => output_port(None, false)?,
+    };
+    port.flush()?;
+    Ok(Unspecified)
+}
+
+pub fn open_input_string(s: &LispVal) -> Result<LispVal, LispErr> {
+    let reader = io::Cursor::new(s.string()?.as_bytes().to_vec());
+    Ok(new_port("string", false, InputPort(reader)))
+}
+
+pub fn open_output_string(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [] => Ok(Port(Rc::new(LispPort::output_buffer("string", false)))),
+        _ => Err(NumArgs(0, LispVal::list(args))),
+    }
+}
+
+pub fn get_output_string(port: &LispVal) -> Result<LispVal, LispErr> {
+    match port {
+        Port(p) if !p.binary => match p.contents() {
+            Some(bytes) => Ok(Str(String::from_utf8_lossy(&bytes).as_ref().into())),
+            None => Err(expected_port("string output", port)),
+        },
+        _ => Err(expected_port("string output", port)),
+    }
+}
+
+// (call-with-output-string proc) calls proc with a new string port, returning what it wrote.
+pub fn call_with_output_string(proc: &LispVal) -> Result<LispVal, LispErr> {
+    let port = open_output_string(&[])?;
+    apply(proc, std::slice::from_ref(&port))?;
+    get_output_string(&port)
+}
+
+// (with-output-to-string thunk) returns what the thunk writes to the current output port.
+pub fn with_output_to_string(thunk: &LispVal) -> Result<LispVal, LispErr> {
+    let port = open_output_string(&[])?;
+    parameters::parameterize(&[(current_output_port(), port.clone())], || {
+        apply(thunk, &[])
+    })?;
+    get_output_string(&port)
+}
+
+// (with-input-from-string string thunk) calls the thunk reading the current input port from the
+// string, and returns its result.
+pub fn with_input_from_string(s: &LispVal, thunk: &LispVal) -> Result<LispVal, LispErr> {
+    let port = open_input_string(s)?;
+    parameters::parameterize(&[(current_input_port(), port)], || apply(thunk, &[]))
+}
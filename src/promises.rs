@@ -0,0 +1,120 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub type Thunk = Rc<dyn Fn() -> Result<LispVal, LispErr>>;
+
+#[derive(Clone)]
+enum PromiseState {
+    Done(LispVal),
+    // made by delay: the thunk computes the value
+    Delayed(Thunk),
+    // made by delay-force: the thunk computes another promise, whose value this promise takes on
+    Lazy(Thunk),
+}
+
+// A promise's state lives in a box that can be shared with another promise.  When forcing a
+// delay-force finds that its thunk returned another promise, the two promises come to share one
+// box (as in SRFI 45), so a long chain of them is forced in a loop, in constant space.
+pub struct LispPromise {
+    state: RefCell<Rc<RefCell<PromiseState>>>,
+    // set on the promises streams are made of, so stream? can tell them from other promises
+    stream: bool,
+}
+
+impl LispPromise {
+    pub fn is_stream(&self) -> bool {
+        self.stream
+    }
+}
+
+impl fmt::Debug for LispPromise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &*self.state.borrow().borrow() {
+            PromiseState::Done(val) => f.debug_tuple("Done").field(val).finish(),
+            _ => write!(f, "Pending"),
+        }
+    }
+}
+
+fn new_promise(state: PromiseState, stream: bool) -> LispVal {
+    Promise(Rc::new(LispPromise {
+        state: RefCell::new(Rc::new(RefCell::new(state))),
+        stream,
+    }))
+}
+
+pub fn delay(thunk: Thunk) -> LispVal {
+    new_promise(PromiseState::Delayed(thunk), false)
+}
+
+pub fn delay_force(thunk: Thunk) -> LispVal {
+    new_promise(PromiseState::Lazy(thunk), false)
+}
+
+// An already forced promise
+pub fn eager(val: LispVal) -> LispVal {
+    new_promise(PromiseState::Done(val), false)
+}
+
+// SRFI 41's stream-lazy and stream-eager: delay_force and eager for promises that are streams
+pub fn stream_lazy(thunk: Thunk) -> LispVal {
+    new_promise(PromiseState::Lazy(thunk), true)
+}
+
+pub fn stream_eager(val: LispVal) -> LispVal {
+    new_promise(PromiseState::Done(val), true)
+}
+
+pub fn make_promise(val: &LispVal) -> Result<LispVal, LispErr> {
+    match val {
+        Promise(_) => Ok(val.clone()),
+        _ => Ok(eager(val.clone())),
+    }
+}
+
+// Forcing anything but a promise just returns it.
+pub fn force(val: &LispVal) -> Result<LispVal, LispErr> {
+    let promise = match val {
+        Promise(promise) => promise.clone(),
+        _ => return Ok(val.clone()),
+    };
+    loop {
+        let state = promise.state.borrow().borrow().clone();
+        match state {
+            PromiseState::Done(val) => return Ok(val),
+            PromiseState::Delayed(thunk) => {
+                let val = thunk()?;
+                // forcing the thunk may have forced this promise too, in which case the first
+                // value computed wins
+                let boxed = promise.state.borrow().clone();
+                if let PromiseState::Done(_) = &*boxed.borrow() {
+                    continue;
+                }
+                boxed.replace(PromiseState::Done(val));
+            }
+            PromiseState::Lazy(thunk) => {
+                let next = match thunk()? {
+                    Promise(next) => next,
+                    val => {
+                        return Err(TypeMismatch(
+                            "delay-force's expression must evaluate to a promise".to_string(),
+                            val,
+                        ))
+                    }
+                };
+                let boxed = promise.state.borrow().clone();
+                if let PromiseState::Done(_) = &*boxed.borrow() {
+                    continue;
+                }
+                // take on the other promise's state, then share our box with it
+                let next_state = next.state.borrow().borrow().clone();
+                boxed.replace(next_state);
+                next.state.replace(boxed);
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::eval::Env;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Each define-record-type makes a new type, distinct from every other even if it has the same name
+// and fields, so types (and their records) are compared by identity.
+#[derive(Debug)]
+pub struct LispRecordType {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct LispRecord {
+    pub record_type: Rc<LispRecordType>,
+    pub fields: RefCell<Vec<LispVal>>,
+}
+
+#[derive(Debug)]
+pub enum RecordProcKind {
+    // The record's field index for each of the constructor's arguments
+    Constructor(Vec<usize>),
+    Predicate,
+    Accessor(usize),
+    Modifier(usize),
+}
+
+// The procedures define-record-type makes for a type
+#[derive(Debug)]
+pub struct LispRecordProc {
+    pub name: String,
+    pub record_type: Rc<LispRecordType>,
+    pub kind: RecordProcKind,
+}
+
+fn bad_form(message: &str, form: &LispVal) -> LispErr {
+    BadSpecialForm(message.to_string(), form.clone())
+}
+
+fn name(val: &LispVal) -> Result<String, LispErr> {
+    match val {
+        Atom(name) => Ok(name.clone()),
+        _ => Err(bad_form("Expected a name in define-record-type", val)),
+    }
+}
+
+// (define-record-type <point> (make-point x y) point? (x point-x set-point-x!) (y point-y))
+pub fn define_record_type(env: &Env, spec: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (type_name, constructor, predicate, field_specs) = match spec {
+        [type_name, constructor, predicate, field_specs @ ..] => {
+            (type_name, constructor, predicate, field_specs)
+        }
+        _ => {
+            return Err(bad_form(
+                "define-record-type needs a type name, constructor and predicate",
+                &LispVal::list(spec),
+            ))
+        }
+    };
+
+    let field_specs = field_specs
+        .iter()
+        .map(|field| match field.list_items() {
+            Ok(items) if (2..=3).contains(&items.len()) => Ok(items),
+            _ => Err(bad_form(
+                "Expected (field accessor [modifier]) in define-record-type",
+                field,
+            )),
+        })
+        .collect::<Result<Vec<Vec<LispVal>>, LispErr>>()?;
+    let fields = field_specs
+        .iter()
+        .map(|field| name(&field[0]))
+        .collect::<Result<Vec<String>, LispErr>>()?;
+
+    // <point> is displayed as just point
+    let type_name = name(type_name)?;
+    let record_type = Rc::new(LispRecordType {
+        name: type_name
+            .trim_start_matches('<')
+            .trim_end_matches('>')
+            .to_string(),
+        fields: fields.clone(),
+    });
+    let define_proc = |name: String, kind: RecordProcKind| {
+        let proc = LispRecordProc {
+            name: name.clone(),
+            record_type: record_type.clone(),
+            kind,
+        };
+        env.define(name, RecordProc(Rc::new(proc)));
+    };
+
+    let constructor_spec = constructor.list_items()?;
+    let (constructor_name, args) = match constructor_spec.split_first() {
+        Some((constructor_name, args)) => (name(constructor_name)?, args),
+        None => return Err(bad_form("Expected a constructor name", constructor)),
+    };
+    let indices = args
+        .iter()
+        .map(|arg| {
+            fields
+                .iter()
+                .position(|field| Some(field) == arg.symbol().ok().as_ref())
+                .ok_or_else(|| bad_form("Constructor argument isn't a field", arg))
+        })
+        .collect::<Result<Vec<usize>, LispErr>>()?;
+    define_proc(constructor_name, RecordProcKind::Constructor(indices));
+    define_proc(name(predicate)?, RecordProcKind::Predicate);
+    for (i, field) in field_specs.iter().enumerate() {
+        define_proc(name(&field[1])?, RecordProcKind::Accessor(i));
+        if let Some(modifier) = field.get(2) {
+            define_proc(name(modifier)?, RecordProcKind::Modifier(i));
+        }
+    }
+
+    env.define(type_name, RecordType(record_type.clone()));
+    Ok(Unspecified)
+}
+
+// Checks that `val` is a record made by this procedure's type.
+fn record(proc: &LispRecordProc, val: &LispVal) -> Result<Rc<LispRecord>, LispErr> {
+    match val {
+        Record(record) if Rc::ptr_eq(&record.record_type, &proc.record_type) => Ok(record.clone()),
+        _ => Err(TypeMismatch(
+            format!("Expected a {} record", proc.record_type.name),
+            val.clone(),
+        )),
+    }
+}
+
+pub fn apply_record_proc(proc: &LispRecordProc, args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match (&proc.kind, args) {
+        (RecordProcKind::Constructor(indices), _) if args.len() == indices.len() => {
+            let mut fields = vec![Nil; proc.record_type.fields.len()];
+            for (&i, arg) in indices.iter().zip(args) {
+                fields[i] = arg.clone();
+            }
+            Ok(Record(Rc::new(LispRecord {
+                record_type: proc.record_type.clone(),
+                fields: RefCell::new(fields),
+            })))
+        }
+        (RecordProcKind::Constructor(indices), _) => {
+            Err(NumArgs(indices.len() as i32, LispVal::list(args)))
+        }
+        (RecordProcKind::Predicate, [val]) => Ok(Bool(record(proc, val).is_ok())),
+        (RecordProcKind::Accessor(i), [val]) => Ok(record(proc, val)?.fields.borrow()[*i].clone()),
+        (RecordProcKind::Modifier(i), [val, new]) => {
+            record(proc, val)?.fields.borrow_mut()[*i] = new.clone();
+            Ok(Unspecified)
+        }
+        (RecordProcKind::Modifier(_), _) => Err(NumArgs(2, LispVal::list(args))),
+        _ => Err(NumArgs(1, LispVal::list(args))),
+    }
+}
@@ -0,0 +1,155 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::eval::apply;
+use crate::promises::{delay, eager, force, stream_eager, stream_lazy};
+use std::rc::Rc;
+
+// Streams follow SRFI 41: a stream is a promise that forces either to the empty list or to a
+// pair of a promise for its first element and the rest of the stream.  The procedures that make
+// streams from other streams don't force anything until their result is forced.
+
+pub fn stream_null() -> LispVal {
+    stream_eager(Nil)
+}
+
+pub fn stream_pair(car: LispVal, cdr: LispVal) -> LispVal {
+    stream_eager(LispVal::cons(car, cdr))
+}
+
+pub fn is_stream(val: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(matches!(val, Promise(p) if p.is_stream())))
+}
+
+// Forces the stream, returning its first element's promise and the rest of the stream, or None
+// if it's empty.
+fn stream_parts(stream: &LispVal) -> Result<Option<(LispVal, LispVal)>, LispErr> {
+    let not_a_stream = || TypeMismatch("Expected a stream".to_string(), stream.clone());
+    match stream {
+        Promise(p) if p.is_stream() => match force(stream)? {
+            Nil => Ok(None),
+            ConsList(cons) => Ok(Some((cons.car(), cons.cdr()))),
+            _ => Err(not_a_stream()),
+        },
+        _ => Err(not_a_stream()),
+    }
+}
+
+fn nonempty_parts(stream: &LispVal) -> Result<(LispVal, LispVal), LispErr> {
+    stream_parts(stream)?
+        .ok_or_else(|| TypeMismatch("Expected a stream pair".to_string(), stream.clone()))
+}
+
+pub fn is_stream_null(stream: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Bool(stream_parts(stream)?.is_none()))
+}
+
+pub fn is_stream_pair(stream: &LispVal) -> Result<LispVal, LispErr> {
+    match stream {
+        Promise(p) if p.is_stream() => Ok(Bool(matches!(force(stream)?, ConsList(_)))),
+        _ => Ok(Bool(false)),
+    }
+}
+
+pub fn stream_car(stream: &LispVal) -> Result<LispVal, LispErr> {
+    force(&nonempty_parts(stream)?.0)
+}
+
+pub fn stream_cdr(stream: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(nonempty_parts(stream)?.1)
+}
+
+pub fn stream_ref(stream: &LispVal, k: &LispVal) -> Result<LispVal, LispErr> {
+    let mut stream = stream.clone();
+    for _ in 0..k.index()? {
+        stream = match stream_parts(&stream)? {
+            Some((_, cdr)) => cdr,
+            None => {
+                return Err(OutOfRange(
+                    "Stream index is out of range".to_string(),
+                    k.clone(),
+                ))
+            }
+        };
+    }
+    stream_car(&stream)
+}
+
+// (stream-take n stream) is a stream of at most the first n elements.
+pub fn stream_take(n: &LispVal, stream: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(take(n.index()?, stream.clone()))
+}
+
+fn take(n: usize, stream: LispVal) -> LispVal {
+    stream_lazy(Rc::new(move || {
+        if n == 0 {
+            return Ok(stream_null());
+        }
+        match stream_parts(&stream)? {
+            Some((car, cdr)) => Ok(stream_pair(car, take(n - 1, cdr))),
+            None => Ok(stream_null()),
+        }
+    }))
+}
+
+pub fn stream_map(f: &LispVal, stream: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(map(f.clone(), stream.clone()))
+}
+
+fn map(f: LispVal, stream: LispVal) -> LispVal {
+    stream_lazy(Rc::new(move || match stream_parts(&stream)? {
+        Some((car, cdr)) => {
+            let g = f.clone();
+            let mapped = delay(Rc::new(move || apply(&g, &[force(&car)?])));
+            Ok(stream_pair(mapped, map(f.clone(), cdr)))
+        }
+        None => Ok(stream_null()),
+    }))
+}
+
+pub fn stream_filter(pred: &LispVal, stream: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(filter(pred.clone(), stream.clone()))
+}
+
+// Skipping a rejected element returns the filtered rest of the stream as another lazy promise,
+// which force handles in a loop, so any number of elements can be skipped in a row.
+fn filter(pred: LispVal, stream: LispVal) -> LispVal {
+    stream_lazy(Rc::new(move || match stream_parts(&stream)? {
+        Some((car, cdr)) => {
+            if let Bool(false) = apply(&pred, &[force(&car)?])? {
+                Ok(filter(pred.clone(), cdr))
+            } else {
+                Ok(stream_pair(car, filter(pred.clone(), cdr)))
+            }
+        }
+        None => Ok(stream_null()),
+    }))
+}
+
+// (stream->list [n] stream) forces the first n elements, or all of them.
+pub fn stream_to_list(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (mut stream, limit) = match args {
+        [stream] => (stream.clone(), None),
+        [n, stream] => (stream.clone(), Some(n.index()?)),
+        _ => return Err(NumArgs(2, LispVal::list(args))),
+    };
+    let mut items = Vec::new();
+    while limit.is_none_or(|n| items.len() < n) {
+        match stream_parts(&stream)? {
+            Some((car, cdr)) => {
+                items.push(force(&car)?);
+                stream = cdr;
+            }
+            None => break,
+        }
+    }
+    Ok(LispVal::list(&items))
+}
+
+pub fn list_to_stream(list: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(list
+        .list_items()?
+        .into_iter()
+        .rev()
+        .fold(stream_null(), |rest, item| stream_pair(eager(item), rest)))
+}
@@ -0,0 +1,353 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Scheme indexes strings by character, while Rust's strings are indexed by byte.  Every index a
+// procedure here takes or returns is a character index.
+//
+// Strings are immutable, so each one is indexed when it's created: we remember the byte offset of
+// every INDEX_STRIDE'th character, so finding any character only scans a short stretch of the
+// string.  ASCII strings, where characters and bytes line up, need no index at all.
+const INDEX_STRIDE: usize = 32;
+
+#[derive(Clone)]
+pub struct LispString {
+    text: Rc<str>,
+    len: usize,
+    offsets: Rc<[usize]>,
+}
+
+impl LispString {
+    pub fn new(text: &str) -> LispString {
+        let (len, offsets) = if text.is_ascii() {
+            (text.len(), Vec::new())
+        } else {
+            let offsets = text
+                .char_indices()
+                .step_by(INDEX_STRIDE)
+                .map(|(offset, _)| offset)
+                .collect();
+            (text.chars().count(), offsets)
+        };
+        LispString {
+            text: text.into(),
+            len,
+            offsets: offsets.into(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    // The length in characters, rather than bytes
+    pub fn char_len(&self) -> usize {
+        self.len
+    }
+
+    // The byte offset of the character at `index`.  The end of the string is a valid index.
+    pub fn byte_offset(&self, index: usize) -> Option<usize> {
+        if index > self.len {
+            None
+        } else if index == self.len {
+            Some(self.text.len())
+        } else if self.offsets.is_empty() {
+            Some(index)
+        } else {
+            let start = self.offsets[index / INDEX_STRIDE];
+            self.text[start..]
+                .char_indices()
+                .nth(index % INDEX_STRIDE)
+                .map(|(offset, _)| start + offset)
+        }
+    }
+
+    // Whether both strings share the same text, rather than just having equal contents
+    pub fn ptr_eq(&self, other: &LispString) -> bool {
+        Rc::ptr_eq(&self.text, &other.text)
+    }
+
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        if index >= self.len {
+            return None;
+        }
+        self.byte_offset(index)
+            .and_then(|offset| self.text[offset..].chars().next())
+    }
+}
+
+impl Deref for LispString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<&str> for LispString {
+    fn from(s: &str) -> LispString {
+        LispString::new(s)
+    }
+}
+
+impl From<String> for LispString {
+    fn from(s: String) -> LispString {
+        LispString::new(&s)
+    }
+}
+
+impl PartialEq for LispString {
+    fn eq(&self, other: &LispString) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for LispString {}
+
+impl Hash for LispString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state)
+    }
+}
+
+impl PartialOrd for LispString {
+    fn partial_cmp(&self, other: &LispString) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LispString {
+    fn cmp(&self, other: &LispString) -> Ordering {
+        self.text.cmp(&other.text)
+    }
+}
+
+impl fmt::Debug for LispString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.text.fmt(f)
+    }
+}
+
+impl fmt::Display for LispString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.text.fmt(f)
+    }
+}
+
+// Resolves the optional `start` and `end` arguments that many string procedures take, returning
+// the selected part of the string.
+pub fn slice<'a>(
+    s: &'a LispString,
+    start: Option<&LispVal>,
+    end: Option<&LispVal>,
+) -> Result<&'a str, LispErr> {
+    let from = match start {
+        Some(start) => s.byte_offset(start.index()?).ok_or_else(|| {
+            OutOfRange(
+                "String start index is out of range".to_string(),
+                start.clone(),
+            )
+        })?,
+        None => 0,
+    };
+    let to = match end {
+        Some(end) => s.byte_offset(end.index()?).ok_or_else(|| {
+            OutOfRange("String end index is out of range".to_string(), end.clone())
+        })?,
+        None => s.len(),
+    };
+    if from > to {
+        return Err(OutOfRange(
+            "String start index is after the end index".to_string(),
+            start.cloned().unwrap_or(Nil),
+        ));
+    }
+    Ok(&s[from..to])
+}
+
+// Calls `f` with a string argument and its optional start and end arguments.
+fn with_slice<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
+where
+    F: Fn(&str) -> LispVal,
+{
+    match args {
+        [s, rest @ ..] if rest.len() <= 2 => Ok(f(slice(&s.string()?, rest.first(), rest.get(1))?)),
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+fn char_position(s: &str, byte_offset: Option<usize>) -> LispVal {
+    match byte_offset {
+        Some(offset) => Number(s[..offset].chars().count().into()),
+        None => Bool(false),
+    }
+}
+
+pub fn string_length(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number(s.string()?.char_len().into()))
+}
+
+pub fn string_ref(s: &LispVal, k: &LispVal) -> Result<LispVal, LispErr> {
+    s.string()?
+        .char_at(k.index()?)
+        .map(Char)
+        .ok_or_else(|| OutOfRange("String index is out of range".to_string(), k.clone()))
+}
+
+pub fn substring(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [_, _, _] => string_copy(args),
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+pub fn string_copy(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    with_slice(|s| Str(s.into()), args)
+}
+
+pub fn string_to_list(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    with_slice(
+        |s| LispVal::list(&s.chars().map(Char).collect::<Vec<_>>()),
+        args,
+    )
+}
+
+pub fn list_to_string(list: &LispVal) -> Result<LispVal, LispErr> {
+    let chars = list
+        .list_items()?
+        .iter()
+        .map(|c| c.character())
+        .collect::<Result<String, LispErr>>()?;
+    Ok(Str(chars.into()))
+}
+
+pub fn string_append(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let strings = args
+        .iter()
+        .map(LispVal::string)
+        .collect::<Result<Vec<LispString>, LispErr>>()?;
+    Ok(Str(strings
+        .iter()
+        .map(LispString::as_str)
+        .collect::<String>()
+        .into()))
+}
+
+// SRFI 13's string-index also accepts predicates and char-sets; we only search for a character.
+pub fn string_index(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [s, c, rest @ ..] if rest.len() <= 2 => {
+            let s = s.string()?;
+            let c = c.character()?;
+            let start = rest.first().map(LispVal::index).transpose()?.unwrap_or(0);
+            let searched = slice(&s, rest.first(), rest.get(1))?;
+            match searched.chars().position(|ch| ch == c) {
+                Some(i) => Ok(Number((start + i).into())),
+                None => Ok(Bool(false)),
+            }
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+pub fn string_contains(s: &LispVal, pattern: &LispVal) -> Result<LispVal, LispErr> {
+    let s = s.string()?;
+    Ok(char_position(&s, s.find(pattern.string()?.as_str())))
+}
+
+pub fn string_join(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let (list, delimiter) = match args {
+        [list] => (list, " ".into()),
+        [list, delimiter] => (list, delimiter.string()?),
+        _ => return Err(NumArgs(2, LispVal::list(args))),
+    };
+    let strings = list
+        .iter()
+        .map(|s| s.string())
+        .collect::<Result<Vec<LispString>, LispErr>>()?;
+    let strings: Vec<&str> = strings.iter().map(LispString::as_str).collect();
+    Ok(Str(strings.join(&delimiter).into()))
+}
+
+// Splits on a delimiter string or character, or on runs of whitespace when no delimiter is given.
+pub fn string_split(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let parts: Vec<LispVal> = match args {
+        [s] => s
+            .string()?
+            .split_whitespace()
+            .map(|p| Str(p.into()))
+            .collect(),
+        [s, Char(c)] => s.string()?.split(*c).map(|p| Str(p.into())).collect(),
+        [s, delimiter] => {
+            let delimiter = delimiter.string()?;
+            if delimiter.is_empty() {
+                return Err(TypeMismatch(
+                    "Expected a non-empty delimiter".to_string(),
+                    Str(delimiter),
+                ));
+            }
+            s.string()?
+                .split(delimiter.as_str())
+                .map(|p| Str(p.into()))
+                .collect()
+        }
+        _ => return Err(NumArgs(2, LispVal::list(args))),
+    };
+    Ok(LispVal::list(&parts))
+}
+
+pub fn string_upcase(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Str(s.string()?.to_uppercase().into()))
+}
+
+pub fn string_downcase(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Str(s.string()?.to_lowercase().into()))
+}
+
+// Full case folding maps each character to a canonical case, possibly changing the length of the
+// string (e.g. "Straße" folds to "strasse").  Lowercasing the uppercase form gets us that for all
+// but a handful of characters.  This maps character by character, since str::to_lowercase would
+// turn a word-final sigma into "ς" rather than "σ".
+pub fn string_foldcase(s: &LispVal) -> Result<LispVal, LispErr> {
+    let folded: String = s
+        .string()?
+        .chars()
+        .flat_map(char::to_uppercase)
+        .flat_map(char::to_lowercase)
+        .collect();
+    Ok(Str(folded.into()))
+}
+
+pub fn string_normalize_nfc(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Str(s.string()?.nfc().collect::<String>().into()))
+}
+
+pub fn string_normalize_nfd(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Str(s.string()?.nfd().collect::<String>().into()))
+}
+
+pub fn string_normalize_nfkc(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Str(s.string()?.nfkc().collect::<String>().into()))
+}
+
+pub fn string_normalize_nfkd(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Str(s.string()?.nfkd().collect::<String>().into()))
+}
+
+// What a user sees as a single character may be several code points, like an "e" followed by a
+// combining accent or a flag emoji.  These split a string into such extended grapheme clusters.
+pub fn string_graphemes(s: &LispVal) -> Result<LispVal, LispErr> {
+    let graphemes: Vec<LispVal> = s.string()?.graphemes(true).map(|g| Str(g.into())).collect();
+    Ok(LispVal::list(&graphemes))
+}
+
+pub fn string_grapheme_count(s: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number(s.string()?.graphemes(true).count().into()))
+}
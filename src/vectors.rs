@@ -0,0 +1,182 @@
+use crate::ast::LispErr::*;
+use crate::ast::LispVal::*;
+use crate::ast::*;
+use crate::eval::apply;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub fn new_vector(vals: Vec<LispVal>) -> LispVal {
+    Vector(Rc::new(RefCell::new(vals)))
+}
+
+fn check_index(vec: &[LispVal], k: &LispVal) -> Result<usize, LispErr> {
+    let i = k.index()?;
+    if i < vec.len() {
+        Ok(i)
+    } else {
+        Err(OutOfRange(
+            format!(
+                "Vector index is out of range for a vector of length {}",
+                vec.len()
+            ),
+            k.clone(),
+        ))
+    }
+}
+
+// A vector of `k` copies of `fill`.  The space is reserved fallibly, so asking for more than can be
+// allocated is an error rather than an abort.  `kind` names the type in error messages.
+pub fn filled<T: Clone>(kind: &str, k: &LispVal, fill: T) -> Result<Vec<T>, LispErr> {
+    let len = k.index()?;
+    let mut vals = Vec::new();
+    vals.try_reserve_exact(len)
+        .map_err(|_| OutOfRange(format!("{} is too large to allocate", kind), k.clone()))?;
+    vals.resize(len, fill);
+    Ok(vals)
+}
+
+// Resolves the optional `start` and `end` arguments that many vector and bytevector procedures
+// take.  `kind` names the type in error messages.
+pub fn range(
+    kind: &str,
+    len: usize,
+    start: Option<&LispVal>,
+    end: Option<&LispVal>,
+) -> Result<(usize, usize), LispErr> {
+    let from = match start {
+        Some(start) => start.index()?,
+        None => 0,
+    };
+    let to = match end {
+        Some(end) => end.index()?,
+        None => len,
+    };
+    if to > len {
+        Err(OutOfRange(
+            format!("{} end index is out of range", kind),
+            end.cloned().unwrap_or(Nil),
+        ))
+    } else if from > to {
+        Err(OutOfRange(
+            format!("{} start index is after the end index", kind),
+            start.cloned().unwrap_or(Nil),
+        ))
+    } else {
+        Ok((from, to))
+    }
+}
+
+// Calls `f` with the selected part of a vector argument, followed by its optional start and end
+// arguments.
+fn with_range<F>(f: F, args: &[LispVal]) -> Result<LispVal, LispErr>
+where
+    F: Fn(&[LispVal]) -> LispVal,
+{
+    match args {
+        [v, rest @ ..] if rest.len() <= 2 => {
+            let vec = v.vector()?;
+            let vec = vec.borrow();
+            let (from, to) = range("Vector", vec.len(), rest.first(), rest.get(1))?;
+            Ok(f(&vec[from..to]))
+        }
+        _ => Err(NumArgs(3, LispVal::list(args))),
+    }
+}
+
+pub fn make_vector(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [k] => Ok(new_vector(filled("Vector", k, Nil)?)),
+        [k, fill] => Ok(new_vector(filled("Vector", k, fill.clone())?)),
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+pub fn vector(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    Ok(new_vector(args.to_vec()))
+}
+
+pub fn vector_length(v: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(Number(v.vector()?.borrow().len().into()))
+}
+
+pub fn vector_ref(v: &LispVal, k: &LispVal) -> Result<LispVal, LispErr> {
+    let vec = v.vector()?;
+    let vec = vec.borrow();
+    Ok(vec[check_index(&vec, k)?].clone())
+}
+
+pub fn vector_set(v: &LispVal, k: &LispVal, obj: &LispVal) -> Result<LispVal, LispErr> {
+    let vec = v.vector()?;
+    let mut vec = vec.borrow_mut();
+    let i = check_index(&vec, k)?;
+    vec[i] = obj.clone();
+    Ok(Unspecified)
+}
+
+pub fn vector_to_list(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    with_range(LispVal::list, args)
+}
+
+pub fn list_to_vector(list: &LispVal) -> Result<LispVal, LispErr> {
+    Ok(new_vector(list.iter().collect()))
+}
+
+pub fn vector_copy(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    with_range(|vals| new_vector(vals.to_vec()), args)
+}
+
+pub fn vector_fill(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [v, fill, rest @ ..] if rest.len() <= 2 => {
+            let vec = v.vector()?;
+            let mut vec = vec.borrow_mut();
+            let (from, to) = range("Vector", vec.len(), rest.first(), rest.get(1))?;
+            for val in vec[from..to].iter_mut() {
+                *val = fill.clone();
+            }
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(4, LispVal::list(args))),
+    }
+}
+
+pub fn vector_append(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    let mut vals = Vec::new();
+    for v in args {
+        vals.extend(v.vector()?.borrow().iter().cloned());
+    }
+    Ok(new_vector(vals))
+}
+
+// Calls `f` on the elements at each index of the given vectors, stopping at the end of the
+// shortest one.  The vectors are copied first, so `f` is free to modify them.
+fn map_vectors(f: &LispVal, vectors: &[LispVal]) -> Result<Vec<LispVal>, LispErr> {
+    let vectors = vectors
+        .iter()
+        .map(|v| Ok(v.vector()?.borrow().clone()))
+        .collect::<Result<Vec<Vec<LispVal>>, LispErr>>()?;
+    let len = vectors.iter().map(Vec::len).min().unwrap_or(0);
+    (0..len)
+        .map(|i| {
+            let args: Vec<LispVal> = vectors.iter().map(|vec| vec[i].clone()).collect();
+            apply(f, &args)
+        })
+        .collect()
+}
+
+pub fn vector_map(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [f, vectors @ ..] if !vectors.is_empty() => Ok(new_vector(map_vectors(f, vectors)?)),
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
+
+pub fn vector_for_each(args: &[LispVal]) -> Result<LispVal, LispErr> {
+    match args {
+        [f, vectors @ ..] if !vectors.is_empty() => {
+            map_vectors(f, vectors)?;
+            Ok(Unspecified)
+        }
+        _ => Err(NumArgs(2, LispVal::list(args))),
+    }
+}
@@ -1,5 +1,6 @@
 use risp::ast::LispVal::*;
 use risp::ast::*;
+use std::cell::RefCell;
 use std::rc::Rc;
 
 #[test]
@@ -16,13 +17,13 @@ fn test_render_false() {
 
 #[test]
 fn test_render_string() {
-    let s = format!("{}", Str("foo".to_string()));
+    let s = format!("{}", Str("foo".into()));
     assert_eq!(s, "\"foo\"")
 }
 
 #[test]
 fn test_render_int() {
-    let s = format!("{}", Number(1));
+    let s = format!("{}", Number(1.into()));
     assert_eq!(s, "1")
 }
 
@@ -37,8 +38,8 @@ fn test_render_one_item_list() {
     let s = format!(
         "{}",
         ConsList(Rc::new(Cons {
-            car: Rc::new(Number(1)),
-            cdr: Rc::new(Nil)
+            car: RefCell::new(Number(1.into())),
+            cdr: RefCell::new(Nil)
         }))
     );
     assert_eq!(s, "(1)")
@@ -48,13 +49,42 @@ fn test_render_one_item_list() {
 fn test_render_two_item_list() {
     let s = format!(
         "{}",
-        LispVal::cons(Number(1), LispVal::cons(Number(2), Nil))
+        LispVal::cons(Number(1.into()), LispVal::cons(Number(2.into()), Nil))
     );
     assert_eq!(s, "(1 2)")
 }
 
 #[test]
 fn test_render_one_item_dotted_list() {
-    let s = format!("{}", LispVal::cons(Nil, Number(1)));
+    let s = format!("{}", LispVal::cons(Nil, Number(1.into())));
     assert_eq!(s, "(() . 1)")
 }
+
+#[test]
+fn test_render_char() {
+    assert_eq!(format!("{}", Char('a')), "#\\a");
+    assert_eq!(format!("{}", Char(' ')), "#\\space");
+    assert_eq!(format!("{}", Char('\u{1}')), "#\\x1");
+    assert_eq!(format!("{}", Char('a').display()), "a");
+}
+
+#[test]
+fn test_display_list() {
+    let list = LispVal::cons(Char('a'), LispVal::cons(Str("b c".into()), Nil));
+    assert_eq!(format!("{}", list), "(#\\a \"b c\")");
+    assert_eq!(format!("{}", list.display()), "(a b c)");
+}
+
+#[test]
+fn test_render_string_escapes() {
+    let s = Str("say \"hi\"\\\n\u{1}".into());
+    assert_eq!(format!("{}", s), r#""say \"hi\"\\\n\x1;""#);
+    assert_eq!(format!("{}", s.display()), "say \"hi\"\\\n\u{1}");
+}
+
+#[test]
+fn test_render_vector() {
+    let v = risp::vectors::new_vector(vec![Number(1.into()), Str("a".into()), Char('b')]);
+    assert_eq!(format!("{}", v), "#(1 \"a\" #\\b)");
+    assert_eq!(format!("{}", v.display()), "#(1 a b)");
+}
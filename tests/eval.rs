@@ -3,13 +3,13 @@ use risp::ast::*;
 use risp::eval::Env;
 use risp::eval::*;
 use risp::parser::parser_combinator;
-use std::collections::hash_map::HashMap;
+use risp::ports::{self, InputPort, OutputBuffer};
 
 fn eval_str(expr: &str) -> Result<LispVal, LispErr> {
-    let mut env: Env = HashMap::new();
-    eval_str_with_env(&mut env, expr)
+    let env = Env::new();
+    eval_str_with_env(&env, expr)
 }
-fn eval_str_with_env(env: &mut Env, expr: &str) -> Result<LispVal, LispErr> {
+fn eval_str_with_env(env: &Env, expr: &str) -> Result<LispVal, LispErr> {
     parser_combinator::scheme(expr).and_then(|ast| eval(env, &ast))
 }
 fn parse(expr: &str) -> Result<LispVal, LispErr> {
@@ -43,7 +43,1667 @@ fn test_cdr() {
 
 #[test]
 fn test_eq() {
-    evals_to("(eq? '(1 2) (cons 1 (cons 2 '())))", "#t");
+    evals_to("(eq? '(1 2) (cons 1 (cons 2 '())))", "#f");
     evals_to("(eq? 1 1)", "#t");
     evals_to("(eq? 1 \"1\")", "#f");
+    evals_to("(eq? 'a 'a)", "#t");
+    evals_to("(eq? \"a\" (string-copy \"a\"))", "#f");
+    evals_to("(eq? car car)", "#t");
+    evals_to("(eq? 100000000000000000000 100000000000000000000)", "#f");
+    let env = Env::new();
+    eval_str_with_env(&env, "(define xs '(1 2))").unwrap();
+    eval_str_with_env(&env, "(define (f) xs)").unwrap();
+    eval_str_with_env(&env, "(define (make-adder n) (lambda (x) (+ x n)))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(eq? xs xs)"), Ok(Bool(true)));
+    assert_eq!(eval_str_with_env(&env, "(eq? xs (f))"), Ok(Bool(true)));
+    assert_eq!(eval_str_with_env(&env, "(eq? f f)"), Ok(Bool(true)));
+    assert_eq!(
+        eval_str_with_env(&env, "(eq? (make-adder 1) (make-adder 1))"),
+        Ok(Bool(false))
+    );
+}
+
+#[test]
+fn test_eqv() {
+    evals_to("(eqv? 100000000000000000000 100000000000000000000)", "#t");
+    evals_to("(eqv? #\\a #\\a)", "#t");
+    evals_to("(eqv? '() '())", "#t");
+    evals_to("(eqv? (vector) (vector))", "#f");
+    evals_to("(eqv? \"\" (string-copy \"\"))", "#f");
+    evals_to("(eqv? '(1) (list->vector '(1)))", "#f");
+}
+
+#[test]
+fn test_equal() {
+    evals_to(
+        "(equal? '(1 (2 #(3 \"four\"))) '(1 (2 #(3 \"four\"))))",
+        "#t",
+    );
+    evals_to(
+        "(equal? '(1 (2 #(3 \"four\"))) '(1 (2 #(3 \"five\"))))",
+        "#f",
+    );
+    evals_to("(equal? #u8(1 2) (bytevector 1 2))", "#t");
+    evals_to("(equal? \"abc\" (string-copy \"abc\"))", "#t");
+    evals_to("(equal? '(1 . 2) '(1 2))", "#f");
+    evals_to("(equal? car car)", "#t");
+    evals_to("(equal? (make-hash-table) (make-hash-table))", "#f");
+
+    let env = Env::new();
+    eval_str_with_env(&env, "(define xs '(1 2))").unwrap();
+    eval_str_with_env(&env, "(set-cdr! (cdr xs) xs)").unwrap();
+    eval_str_with_env(&env, "(define ys '(1 2 1))").unwrap();
+    eval_str_with_env(&env, "(set-cdr! (cdr (cdr ys)) (cdr ys))").unwrap();
+    eval_str_with_env(&env, "(define zs '(1 3))").unwrap();
+    eval_str_with_env(&env, "(set-cdr! (cdr zs) zs)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(equal? xs ys)"), Ok(Bool(true)));
+    assert_eq!(eval_str_with_env(&env, "(equal? xs zs)"), Ok(Bool(false)));
+    assert_eq!(eval_str_with_env(&env, "(eqv? xs ys)"), Ok(Bool(false)));
+}
+
+#[test]
+fn test_bitwise_ops() {
+    evals_to("(bitwise-and 12 10)", "8");
+    evals_to("(bitwise-ior 12 10)", "14");
+    evals_to("(bitwise-or 12 10 1)", "15");
+    evals_to("(bitwise-xor 12 10)", "6");
+    evals_to("(bitwise-not (bitwise-and))", "0");
+    assert_eq!(eval_str("(bitwise-not 5)"), Ok(Number((-6).into())));
+    evals_to("(bitwise-and (- 0 1) 7)", "7");
+    evals_to("(bitwise-if 12 5 10)", "6");
+}
+
+#[test]
+fn test_bit_queries() {
+    evals_to("(arithmetic-shift 1 70)", "1180591620717411303424");
+    evals_to("(arithmetic-shift 1180591620717411303424 (- 0 69))", "2");
+    assert_eq!(
+        eval_str("(arithmetic-shift (- 0 5) (- 0 1))"),
+        Ok(Number((-3).into()))
+    );
+    evals_to("(bit-count 13)", "3");
+    evals_to("(bit-count (- 0 13))", "2");
+    evals_to("(integer-length 255)", "8");
+    evals_to("(integer-length (- 0 256))", "8");
+    evals_to("(bit-set? 2 4)", "#t");
+    evals_to("(bit-set? 100 (- 0 1))", "#t");
+    evals_to("(copy-bit 0 4 #t)", "5");
+    evals_to("(bit-swap 0 2 1)", "4");
+    evals_to("(first-set-bit 40)", "3");
+    evals_to("(bit-field 54 1 4)", "3");
+
+    // bignums, and indexes far past the end of a number, without building huge masks
+    evals_to("(copy-bit 100 (arithmetic-shift 1 100) #f)", "0");
+    evals_to(
+        "(= (copy-bit 0 (arithmetic-shift 1 100) #t) (+ (arithmetic-shift 1 100) 1))",
+        "#t",
+    );
+    evals_to("(bit-field (arithmetic-shift 3 100) 100 200)", "3");
+    evals_to("(bit-field (- 0 1) 0 4)", "15");
+    evals_to("(copy-bit 100000000000 5 #f)", "5");
+    assert_eq!(
+        eval_str("(copy-bit 100000000000 (- 0 1) #t)"),
+        Ok(Number((-1).into()))
+    );
+    evals_to("(bit-field 54 1 100000000000)", "27");
+    evals_to("(bit-field 54 100000000000 100000000001)", "0");
+    evals_to("(bit-swap 0 100000000000 4)", "4");
+    assert!(matches!(
+        eval_str("(copy-bit 1000000000000000000 0 #t)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+}
+
+#[test]
+fn test_bitwise_type_mismatch() {
+    assert!(matches!(
+        eval_str("(bitwise-and 1 \"1\")"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(bit-count #t)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_char_conversions() {
+    evals_to("(char->integer #\\A)", "65");
+    evals_to("(integer->char 955)", "#\\λ");
+    evals_to("(char? #\\a)", "#t");
+    evals_to("(char? \"a\")", "#f");
+    assert!(eval_str("(integer->char 55296)").is_err());
+}
+
+#[test]
+fn test_char_comparisons() {
+    evals_to("(char<? #\\a #\\b #\\c)", "#t");
+    evals_to("(char<? #\\a #\\c #\\b)", "#f");
+    evals_to("(char=? #\\a #\\A)", "#f");
+    evals_to("(char-ci=? #\\a #\\A)", "#t");
+    evals_to("(char-ci<? #\\a #\\B)", "#t");
+    evals_to("(char-ci=? #\\σ #\\Σ #\\ς)", "#t");
+}
+
+#[test]
+fn test_char_classes() {
+    evals_to("(char-upcase #\\ä)", "#\\Ä");
+    evals_to("(char-downcase #\\Λ)", "#\\λ");
+    evals_to("(char-upcase #\\ß)", "#\\ß");
+    evals_to("(char-alphabetic? #\\λ)", "#t");
+    evals_to("(char-alphabetic? #\\1)", "#f");
+    evals_to("(char-numeric? #\\٣)", "#t");
+    evals_to("(char-whitespace? #\\tab)", "#t");
+    evals_to("(digit-value #\\7)", "7");
+    evals_to("(digit-value #\\٣)", "3");
+    evals_to("(digit-value #\\a)", "#f");
+}
+
+#[test]
+fn test_string_comparisons() {
+    evals_to("(string=? \"a\" \"a\" \"a\")", "#t");
+    evals_to("(string=? \"a\" \"a\" \"b\")", "#f");
+    evals_to("(string<? \"a\" \"b\" \"c\")", "#t");
+    evals_to("(string>=? \"b\" \"b\" \"a\")", "#t");
+}
+
+#[test]
+fn test_string_indexing() {
+    evals_to("(string-length \"λx.x\")", "4");
+    evals_to("(string-ref \"λx.x\" 1)", "#\\x");
+    evals_to("(string-ref \"naïve\" 2)", "#\\ï");
+    evals_to("(substring \"naïve café\" 2 8)", "\"ïve ca\"");
+    evals_to("(string-copy \"naïve\" 2)", "\"ïve\"");
+    evals_to("(string->list \"aλb\")", "(#\\a #\\λ #\\b)");
+    assert!(matches!(
+        eval_str("(string-ref \"abc\" 3)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(substring \"abc\" 2 1)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+}
+
+#[test]
+fn test_string_conversions() {
+    evals_to("(string-append \"foo\" \"\" \"bar\")", "\"foobar\"");
+    evals_to("(list->string (cons #\\h (cons #\\i '())))", "\"hi\"");
+    assert!(eval_str("(list->string (cons #\\a #\\b))").is_err());
+    evals_to("(string->symbol \"foo\")", "foo");
+    evals_to("(symbol->string 'foo)", "\"foo\"");
+    evals_to("(string-upcase \"straße\")", "\"STRASSE\"");
+    evals_to("(string-downcase \"ΛΑ\")", "\"λα\"");
+}
+
+#[test]
+fn test_string_search() {
+    evals_to("(string-index \"héllo\" #\\l)", "2");
+    evals_to("(string-index \"héllo\" #\\l 3)", "3");
+    evals_to("(string-index \"héllo\" #\\z)", "#f");
+    evals_to("(string-contains \"ünïcode\" \"code\")", "3");
+    evals_to("(string-contains \"abc\" \"d\")", "#f");
+    evals_to("(string-join '(\"a\" \"b\" \"c\") \", \")", "\"a, b, c\"");
+    evals_to("(string-join '(\"a\" \"b\"))", "\"a b\"");
+    evals_to("(string-split \"a,b,,c\" #\\,)", "(\"a\" \"b\" \"\" \"c\")");
+    evals_to("(string-split \"a::b\" \"::\")", "(\"a\" \"b\")");
+    evals_to("(string-split \"  a  b \")", "(\"a\" \"b\")");
+}
+
+#[test]
+fn test_unicode_strings() {
+    evals_to("(string-foldcase \"Straße ΣΑΣ\")", "\"strasse σασ\"");
+    evals_to(
+        "(string=? (string-normalize-nfc \"e\\x301;\") \"\\xe9;\")",
+        "#t",
+    );
+    evals_to("(string-length (string-normalize-nfd \"\\xe9;\"))", "2");
+    evals_to("(string-normalize-nfkc \"ﬁ\")", "\"fi\"");
+    evals_to(
+        "(string-graphemes \"e\\x301;a🇧🇷\")",
+        "(\"e\\x301;\" \"a\" \"🇧🇷\")",
+    );
+    evals_to("(string-grapheme-count \"e\\x301;a🇧🇷\")", "3");
+    evals_to("(string-length \"e\\x301;a🇧🇷\")", "5");
+}
+
+#[test]
+fn test_define_and_closures() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define x (+ 1 2))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "x"), parse("3"));
+    eval_str_with_env(
+        &env,
+        "(define (fact n) (if (= n 0) 1 (* n (fact (- n 1)))))",
+    )
+    .unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(fact 20)"),
+        parse("2432902008176640000")
+    );
+    eval_str_with_env(&env, "(define (adder n) (lambda (x) (+ x n)))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "((adder 2) 3)"), parse("5"));
+    eval_str_with_env(&env, "(define (rest x . xs) xs)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(rest 1 2 3)"), parse("(2 3)"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(fact 1 2)"),
+        Err(LispErr::NumArgs(1, _))
+    ));
+}
+
+#[test]
+fn test_set_in_closure() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define count 0)").unwrap();
+    eval_str_with_env(&env, "(define (bump) (set! count (+ count 1)))").unwrap();
+    eval_str_with_env(&env, "(bump)").unwrap();
+    eval_str_with_env(&env, "(bump)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "count"), parse("2"));
+    assert!(matches!(
+        eval_str("(set! undefined 1)"),
+        Err(LispErr::UnboundVar(_, _))
+    ));
+}
+
+#[test]
+fn test_vectors() {
+    evals_to("#(1 #t \"a\")", "#(1 #t \"a\")");
+    evals_to("(vector 1 2 3)", "#(1 2 3)");
+    evals_to("(make-vector 2 'a)", "#(a a)");
+    assert!(matches!(
+        eval_str("(make-vector 100000000000000000 0)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    evals_to("(vector-length #(1 2 3))", "3");
+    evals_to("(vector-ref #(1 2 3) 1)", "2");
+    evals_to("(vector->list #(1 2 3 4) 1 3)", "(2 3)");
+    evals_to("(list->vector '(1 2))", "#(1 2)");
+    evals_to("(vector-copy #(1 2 3) 1)", "#(2 3)");
+    evals_to("(vector-append #(1) #() #(2 3))", "#(1 2 3)");
+    evals_to("(vector-map + #(1 2 3) #(10 20))", "#(11 22)");
+    evals_to("(vector-map (lambda (x) (* x x)) #(1 2 3))", "#(1 4 9)");
+}
+
+#[test]
+fn test_vector_mutation() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define v (make-vector 3 0))").unwrap();
+    eval_str_with_env(&env, "(vector-set! v 0 'x)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "v"), parse("#(x 0 0)"));
+    eval_str_with_env(&env, "(vector-fill! v 7 1)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "v"), parse("#(x 7 7)"));
+    eval_str_with_env(&env, "(define total 0)").unwrap();
+    eval_str_with_env(
+        &env,
+        "(vector-for-each (lambda (x) (set! total (+ total x))) #(1 2 3))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "total"), parse("6"));
+}
+
+#[test]
+fn test_vector_bounds() {
+    assert!(matches!(
+        eval_str("(vector-ref #(1 2 3) 3)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(vector-set! #() 0 1)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(vector-copy #(1 2 3) 2 1)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(vector-ref '(1 2) 0)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_bytevectors() {
+    evals_to("#u8(1 2 255)", "#u8(1 2 255)");
+    evals_to("(bytevector 1 2 3)", "#u8(1 2 3)");
+    evals_to("(make-bytevector 2 7)", "#u8(7 7)");
+    assert!(matches!(
+        eval_str("(make-bytevector 1000000000000000000)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    evals_to("(bytevector-length #u8(1 2 3))", "3");
+    evals_to("(bytevector-u8-ref #u8(1 2 3) 2)", "3");
+    evals_to("(bytevector-copy #u8(1 2 3 4) 1 3)", "#u8(2 3)");
+    evals_to("(bytevector-append #u8(1) #u8() #u8(2))", "#u8(1 2)");
+    evals_to("(utf8->string #u8(206 187 120))", "\"λx\"");
+    evals_to("(string->utf8 \"aλ\")", "#u8(97 206 187)");
+    evals_to("(string->utf8 \"aλb\" 1 2)", "#u8(206 187)");
+    evals_to("(bytevector? #u8())", "#t");
+    evals_to("(bytevector? #(1))", "#f");
+}
+
+#[test]
+fn test_bytevector_mutation() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define bv (make-bytevector 4 0))").unwrap();
+    eval_str_with_env(&env, "(bytevector-u8-set! bv 1 255)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "bv"), parse("#u8(0 255 0 0)"));
+    eval_str_with_env(&env, "(bytevector-copy! bv 2 #u8(9 8 7) 1)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "bv"), parse("#u8(0 255 8 7)"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(bytevector-u8-set! bv 4 1)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str_with_env(&env, "(bytevector-u8-set! bv 0 256)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_bytevector_multibyte() {
+    evals_to("(bytevector-u16-ref #u8(1 2) 0 'little)", "513");
+    evals_to("(bytevector-u16-ref #u8(1 2) 0 'big)", "258");
+    evals_to(
+        "(bytevector-u32-ref #u8(0 222 173 190 239) 1 'big)",
+        "3735928559",
+    );
+    assert_eq!(
+        eval_str("(bytevector-s16-ref #u8(255 255) 0 'big)"),
+        Ok(Number((-1).into()))
+    );
+    evals_to(
+        "(bytevector-u64-ref #u8(255 255 255 255 255 255 255 255) 0 'big)",
+        "18446744073709551615",
+    );
+    let env = Env::new();
+    eval_str_with_env(&env, "(define bv (make-bytevector 4 0))").unwrap();
+    eval_str_with_env(&env, "(bytevector-u32-set! bv 0 3735928559 'little)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "bv"), parse("#u8(239 190 173 222)"));
+    eval_str_with_env(&env, "(bytevector-s16-set! bv 2 (- 0 2) 'big)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "bv"), parse("#u8(239 190 255 254)"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(bytevector-s16-set! bv 0 32768 'big)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str_with_env(&env, "(bytevector-u32-ref bv 1 'big)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str_with_env(&env, "(bytevector-u16-ref bv 0 'middle)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    // indexes near usize::MAX are out of range, rather than overflowing
+    assert!(matches!(
+        eval_str("(bytevector-u8-ref #u8(1 2) 18446744073709551615)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(bytevector-u32-ref #u8(1 2) 18446744073709551614 'big)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+}
+
+#[test]
+fn test_hash_tables() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define t (make-hash-table))").unwrap();
+    eval_str_with_env(&env, "(hash-table-set! t '(1 2) \"list\")").unwrap();
+    eval_str_with_env(&env, "(hash-table-set! t #(a b) 'vector)").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref t (cons 1 (cons 2 '())))"),
+        parse("\"list\"")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref t (vector 'a 'b))"),
+        parse("vector")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref t 'missing (lambda () 0))"),
+        parse("0")
+    );
+    assert!(matches!(
+        eval_str_with_env(&env, "(hash-table-ref t 'missing)"),
+        Err(LispErr::Default(_))
+    ));
+    eval_str_with_env(&env, "(hash-table-delete! t #(a b))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(hash-table-size t)"), parse("1"));
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-keys t)"),
+        parse("((1 2))")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table->alist t)"),
+        parse("(((1 2) . \"list\"))")
+    );
+}
+
+#[test]
+fn test_hash_table_equivalence() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define s \"key\")").unwrap();
+    eval_str_with_env(&env, "(define eqv-table (make-hash-table eqv?))").unwrap();
+    eval_str_with_env(&env, "(hash-table-set! eqv-table s 1)").unwrap();
+    eval_str_with_env(&env, "(hash-table-set! eqv-table 100 2)").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref/default eqv-table s 0)"),
+        parse("1")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref/default eqv-table (string-copy s) 0)"),
+        parse("0")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref/default eqv-table (+ 99 1) 0)"),
+        parse("2")
+    );
+
+    eval_str_with_env(&env, "(define string-table (make-hash-table string=?))").unwrap();
+    eval_str_with_env(&env, "(hash-table-set! string-table s 1)").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref string-table (string-copy s))"),
+        parse("1")
+    );
+    assert!(matches!(
+        eval_str_with_env(&env, "(hash-table-set! string-table 'key 2)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(make-hash-table car)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_hash_table_update() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define counts (make-hash-table))").unwrap();
+    eval_str_with_env(
+        &env,
+        "(define (count! word) (hash-table-update! counts word (lambda (n) (+ n 1)) (lambda () 0)))",
+    )
+    .unwrap();
+    eval_str_with_env(&env, "(count! 'a)").unwrap();
+    eval_str_with_env(&env, "(count! 'b)").unwrap();
+    eval_str_with_env(&env, "(count! 'a)").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref counts 'a)"),
+        parse("2")
+    );
+    eval_str_with_env(
+        &env,
+        "(hash-table-update!/default counts 'c (lambda (n) (* n 10)) 1)",
+    )
+    .unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref counts 'c)"),
+        parse("10")
+    );
+
+    eval_str_with_env(&env, "(define total 0)").unwrap();
+    eval_str_with_env(
+        &env,
+        "(hash-table-walk counts (lambda (k v) (set! total (+ total v))))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "total"), parse("13"));
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(hash-table-ref (alist->hash-table '((a . 1) (a . 2))) 'a)"
+        ),
+        parse("1")
+    );
+}
+
+#[test]
+fn test_pair_mutation() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define p (cons 1 2))").unwrap();
+    eval_str_with_env(&env, "(define alias p)").unwrap();
+    eval_str_with_env(&env, "(set-car! p 10)").unwrap();
+    eval_str_with_env(&env, "(set-cdr! alias '(20))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "p"), parse("(10 20)"));
+    assert_eq!(eval_str_with_env(&env, "alias"), parse("(10 20)"));
+    assert!(matches!(
+        eval_str("(set-car! '() 1)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_circular_lists() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define xs '(1 2 3))").unwrap();
+    eval_str_with_env(&env, "(set-cdr! (cdr (cdr xs)) xs)").unwrap();
+    let xs = eval_str_with_env(&env, "xs").unwrap();
+    assert_eq!(format!("{}", xs), "#0=(1 2 3 . #0#)");
+    assert_eq!(xs.iter().count(), 3);
+    assert_eq!(
+        eval_str_with_env(&env, "(list->vector xs)"),
+        parse("#(1 2 3)")
+    );
+
+    // the same cycle, entered from a different point and unrolled once
+    eval_str_with_env(&env, "(define ys '(1 2 3 1 2 3))").unwrap();
+    eval_str_with_env(&env, "(set-cdr! (cdr (cdr (cdr (cdr (cdr ys))))) ys)").unwrap();
+    let ys = eval_str_with_env(&env, "ys").unwrap();
+    assert_eq!(xs, ys);
+    assert_ne!(xs, parse("(1 2 3)").unwrap());
+
+    eval_str_with_env(&env, "(define t (make-hash-table))").unwrap();
+    eval_str_with_env(&env, "(hash-table-set! t xs 'found)").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(hash-table-ref t ys)"),
+        parse("found")
+    );
+
+    eval_str_with_env(&env, "(define v (vector 1 xs))").unwrap();
+    eval_str_with_env(&env, "(vector-set! v 0 v)").unwrap();
+    assert_eq!(
+        format!("{}", eval_str_with_env(&env, "v").unwrap()),
+        "#0=#(#0# #1=(1 2 3 . #1#))"
+    );
+}
+
+#[test]
+fn test_list_basics() {
+    evals_to("(list 1 2 3)", "(1 2 3)");
+    evals_to("(length '(1 2 3))", "3");
+    evals_to("(append '(1) '(2 3) '() '(4))", "(1 2 3 4)");
+    evals_to("(append '(1) 2)", "(1 . 2)");
+    evals_to("(reverse '(1 2 3))", "(3 2 1)");
+    evals_to("(list-ref '(a b c) 1)", "b");
+    evals_to("(list-tail '(a b c) 2)", "(c)");
+    evals_to("(take '(a b c) 2)", "(a b)");
+    evals_to("(drop '(a b c) 2)", "(c)");
+    evals_to("(last-pair '(1 2 . 3))", "(2 . 3)");
+    evals_to("(iota 4)", "(0 1 2 3)");
+    evals_to("(iota 3 1 2)", "(1 3 5)");
+    evals_to("(iota 0 5)", "()");
+    assert!(matches!(
+        eval_str("(length '(1 2 . 3))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(list-ref '(a b c) 3)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    let env = Env::new();
+    eval_str_with_env(&env, "(define xs (list 1 2))").unwrap();
+    eval_str_with_env(&env, "(set-cdr! (cdr xs) xs)").unwrap();
+    assert!(matches!(
+        eval_str_with_env(&env, "(length xs)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_list_higher_order() {
+    evals_to("(map (lambda (x) (* x x)) '(1 2 3))", "(1 4 9)");
+    evals_to("(map + '(1 2 3) '(10 20))", "(11 22)");
+    evals_to("(filter (lambda (x) (> x 1)) '(1 2 3))", "(2 3)");
+    evals_to("(remove (lambda (x) (> x 1)) '(1 2 3))", "(1)");
+    evals_to(
+        "(call-with-values (lambda () (partition (lambda (x) (> x 1)) '(1 2 3))) list)",
+        "((2 3) (1))",
+    );
+    evals_to("(fold cons '() '(1 2 3))", "(3 2 1)");
+    evals_to("(fold-left cons '() '(1 2))", "((() . 1) . 2)");
+    evals_to("(fold-right cons '() '(1 2 3))", "(1 2 3)");
+    evals_to("(reduce + 0 '(1 2 3 4))", "10");
+    evals_to("(reduce + 0 '())", "0");
+    evals_to("(any (lambda (x) (> x 1)) '(1 2 3))", "#t");
+    evals_to("(any (lambda (x) (> x 5)) '(1 2 3))", "#f");
+    evals_to("(every (lambda (x) (> x 0)) '(1 2 3))", "#t");
+    evals_to("(every (lambda (x) (> x 1)) '(1 2 3))", "#f");
+
+    let env = Env::new();
+    eval_str_with_env(&env, "(define total 0)").unwrap();
+    eval_str_with_env(
+        &env,
+        "(for-each (lambda (x) (set! total (+ total x))) '(1 2 3))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "total"), parse("6"));
+
+    // partition calls the predicate once per element
+    eval_str_with_env(&env, "(define calls 0)").unwrap();
+    eval_str_with_env(
+        &env,
+        "(partition (lambda (x) (set! calls (+ calls 1)) (> x 1)) '(1 2 3))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "calls"), parse("3"));
+}
+
+#[test]
+fn test_list_searching() {
+    evals_to("(memq 'c '(a b c d))", "(c d)");
+    evals_to("(memq 'e '(a b c d))", "#f");
+    evals_to("(memv 101 '(100 101 102))", "(101 102)");
+    evals_to("(member '(1) '(0 (1) 2))", "((1) 2)");
+    evals_to("(member 2 '(1 2 3) (lambda (x y) (< x y)))", "(3)");
+    evals_to("(assq 'b '((a 1) (b 2)))", "(b 2)");
+    evals_to("(assv 2 '((1 one) (2 two)))", "(2 two)");
+    evals_to("(assoc '(a) '(((a) 1) ((b) 2)))", "((a) 1)");
+    evals_to("(assoc 'c '((a 1) (b 2)))", "#f");
+    evals_to("(delete 2 '(1 2 3 2))", "(1 3)");
+    evals_to("(delete-duplicates '(a b a c b))", "(a b c)");
+}
+
+#[test]
+fn test_type_predicates() {
+    evals_to("(pair? '(1))", "#t");
+    evals_to("(pair? '())", "#f");
+    evals_to("(null? '())", "#t");
+    evals_to("(null? '(1))", "#f");
+    evals_to("(list? '(1 2))", "#t");
+    evals_to("(list? '())", "#t");
+    evals_to("(list? '(1 . 2))", "#f");
+    evals_to("(symbol? 'a)", "#t");
+    evals_to("(symbol? \"a\")", "#f");
+    evals_to("(string? \"a\")", "#t");
+    evals_to("(number? 1)", "#t");
+    evals_to("(number? 'one)", "#f");
+    evals_to("(boolean? #f)", "#t");
+    evals_to("(boolean? '())", "#f");
+    evals_to("(procedure? car)", "#t");
+    evals_to("(procedure? (lambda (x) x))", "#t");
+    evals_to("(procedure? 'car)", "#f");
+    evals_to("(char? #\\a)", "#t");
+    evals_to("(vector? #(1))", "#t");
+    evals_to("(vector? '(1))", "#f");
+
+    let env = Env::new();
+    eval_str_with_env(&env, "(define xs (list 1 2))").unwrap();
+    eval_str_with_env(&env, "(set-cdr! (cdr xs) xs)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(list? xs)"), Ok(Bool(false)));
+}
+
+#[test]
+fn test_symbols() {
+    evals_to("(symbol->string 'abc)", "\"abc\"");
+    evals_to("(string->symbol \"abc\")", "abc");
+    evals_to("(symbol=? 'a 'a 'a)", "#t");
+    evals_to("(symbol=? 'a 'b)", "#f");
+    assert!(matches!(
+        eval_str("(symbol=? 'a \"a\")"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    evals_to("(symbol? (gensym))", "#t");
+    evals_to("(eq? (gensym) (gensym))", "#f");
+    let sym = eval_str("(gensym \"tmp\")").unwrap().symbol().unwrap();
+    assert!(sym.starts_with("tmp"));
+    let sym = eval_str("(generate-uninterned-symbol 'x)")
+        .unwrap()
+        .symbol()
+        .unwrap();
+    assert!(sym.starts_with('x'));
+
+    // an uninterned symbol is only the same as itself, not as a symbol with the same name
+    let env = Env::new();
+    eval_str_with_env(&env, "(define g (gensym))").unwrap();
+    eval_str_with_env(&env, "(define name (string->symbol (symbol->string g)))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(eq? g g)"), parse("#t"));
+    assert_eq!(eval_str_with_env(&env, "(eq? g name)"), parse("#f"));
+    assert_eq!(eval_str_with_env(&env, "(equal? g name)"), parse("#f"));
+    assert_eq!(eval_str_with_env(&env, "(symbol=? g g)"), parse("#t"));
+    assert_eq!(eval_str_with_env(&env, "(symbol=? g name)"), parse("#f"));
+
+    // and names a different variable, so generated code can bind it without capturing anything
+    let run = |expr| eval_str_with_env(&env, &format!("(eval {} (interaction-environment))", expr));
+    run("(list 'define g 1)").unwrap();
+    assert_eq!(run("g"), parse("1"));
+    assert!(matches!(run("name"), Err(LispErr::UnboundVar(_, _))));
+    run("(list 'define name 10)").unwrap();
+    run("(list 'set! g 2)").unwrap();
+    assert_eq!(run("g"), parse("2"));
+    assert_eq!(run("name"), parse("10"));
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "((eval (list 'lambda (list g) g) (interaction-environment)) 3)"
+        ),
+        parse("3")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "((eval (list 'lambda (list g) name) (interaction-environment)) 3)"
+        ),
+        parse("10")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "((eval (list 'lambda (list 'a '#!optional g) (list '+ 'a g)) (interaction-environment)) 1 2)"
+        ),
+        parse("3")
+    );
+}
+
+#[test]
+fn test_apply() {
+    evals_to("(apply + '(1 2 3))", "6");
+    evals_to("(apply + 1 2 '(3 4))", "10");
+    evals_to("(apply (lambda (x . rest) rest) 1 '(2 3))", "(2 3)");
+    evals_to("(apply apply (list + (list 1 2)))", "3");
+    evals_to("(map apply (list + *) '((1 2) (3 4)))", "(3 12)");
+    assert!(matches!(
+        eval_str("(apply + 1 2)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(eval_str("(apply +)"), Err(LispErr::NumArgs(_, _))));
+
+    let env = Env::new();
+    eval_str_with_env(&env, "(define (wrap f) (lambda args (apply f args)))").unwrap();
+    eval_str_with_env(&env, "(define add (wrap +))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(add 1 2 3)"), parse("6"));
+}
+
+#[test]
+fn test_tail_calls() {
+    let env = Env::new();
+    eval_str_with_env(
+        &env,
+        "(define (count-down n) (if (= n 0) 'done (count-down (- n 1))))",
+    )
+    .unwrap();
+    eval_str_with_env(
+        &env,
+        "(define (apply-down n) (if (= n 0) 'done (apply apply-down (list (- n 1)))))",
+    )
+    .unwrap();
+    eval_str_with_env(&env, "(define (even? n) (if (= n 0) #t (odd? (- n 1))))").unwrap();
+    eval_str_with_env(&env, "(define (odd? n) (if (= n 0) #f (even? (- n 1))))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(count-down 10000)"), parse("done"));
+    assert_eq!(eval_str_with_env(&env, "(apply-down 10000)"), parse("done"));
+    assert_eq!(eval_str_with_env(&env, "(even? 10001)"), parse("#f"));
+}
+
+#[test]
+fn test_records() {
+    let env = Env::new();
+    eval_str_with_env(
+        &env,
+        "(define-record-type <point> (make-point x y) point? (x point-x set-point-x!) (y point-y))",
+    )
+    .unwrap();
+    eval_str_with_env(&env, "(define p (make-point 1 2))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(point-x p)"), parse("1"));
+    assert_eq!(eval_str_with_env(&env, "(point-y p)"), parse("2"));
+    assert_eq!(eval_str_with_env(&env, "(point? p)"), parse("#t"));
+    assert_eq!(eval_str_with_env(&env, "(point? '(1 2))"), parse("#f"));
+    eval_str_with_env(&env, "(set-point-x! p 10)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(point-x p)"), parse("10"));
+    assert_eq!(
+        format!("{}", eval_str_with_env(&env, "p").unwrap()),
+        "#<point x: 10 y: 2>"
+    );
+    assert_eq!(eval_str_with_env(&env, "(procedure? point-x)"), parse("#t"));
+    assert_eq!(
+        eval_str_with_env(&env, "(map point-y (list p (make-point 3 4)))"),
+        parse("(2 4)")
+    );
+
+    // records are only equal to themselves
+    assert_eq!(
+        eval_str_with_env(&env, "(equal? (make-point 1 2) (make-point 1 2))"),
+        parse("#f")
+    );
+    assert_eq!(eval_str_with_env(&env, "(eq? p p)"), parse("#t"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(make-point 1)"),
+        Err(LispErr::NumArgs(2, _))
+    ));
+    assert!(matches!(
+        eval_str_with_env(&env, "(point-x '(1 2))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_record_type_identity() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define-record-type node (make-node val) node? (val node-val) (next node-next set-node-next!))").unwrap();
+    eval_str_with_env(&env, "(define n (make-node \"a\"))").unwrap();
+    eval_str_with_env(&env, "(set-node-next! n n)").unwrap();
+    assert_eq!(
+        format!("{}", eval_str_with_env(&env, "n").unwrap()),
+        "#0=#<node val: \"a\" next: #0#>"
+    );
+
+    // a second definition makes a distinct type, even with the same name and fields
+    eval_str_with_env(&env, "(define old-node? node?)").unwrap();
+    eval_str_with_env(&env, "(define-record-type node (make-node val) node? (val node-val) (next node-next set-node-next!))").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(old-node? (make-node 1))"),
+        parse("#f")
+    );
+    assert!(matches!(
+        eval_str("(define-record-type point (make-point z) point? (x point-x))"),
+        Err(LispErr::BadSpecialForm(_, _))
+    ));
+}
+
+#[test]
+fn test_values() {
+    evals_to("(values 1)", "1");
+    assert_eq!(
+        eval_str("(values 1 2)"),
+        Ok(Values(vec![Number(1.into()), Number(2.into())]))
+    );
+    assert_eq!(eval_str("(values)"), Ok(Values(vec![])));
+    evals_to("(call-with-values (lambda () (values 1 2)) +)", "3");
+    evals_to("(call-with-values (lambda () 5) list)", "(5)");
+    evals_to("(call-with-values (lambda () (values)) list)", "()");
+    evals_to("(call-with-values (lambda () (floor/ 7 2)) list)", "(3 1)");
+    assert_eq!(
+        eval_str("(floor/ (- 0 7) 2)"),
+        Ok(Values(vec![Number((-4).into()), Number(1.into())]))
+    );
+    assert_eq!(
+        eval_str("(truncate/ (- 0 7) 2)"),
+        Ok(Values(vec![Number((-3).into()), Number((-1).into())]))
+    );
+    evals_to(
+        "(call-with-values (lambda () (exact-integer-sqrt 17)) list)",
+        "(4 1)",
+    );
+    assert!(matches!(eval_str("(floor/ 1 0)"), Err(LispErr::Default(_))));
+    assert!(matches!(
+        eval_str("(call-with-values (lambda () (values 1 2)) (lambda (x) x))"),
+        Err(LispErr::NumArgs(1, _))
+    ));
+    // multiple values can't be passed as one argument
+    assert!(matches!(
+        eval_str("(list (values 1 2))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(list (values))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    evals_to("(list (values 1))", "(1)");
+}
+
+#[test]
+fn test_binding_values() {
+    evals_to(
+        "(let-values (((q r) (floor/ 7 2)) ((all) (values 1))) (list q r all))",
+        "(3 1 1)",
+    );
+    evals_to(
+        "(let-values (((a . rest) (values 1 2 3)) (all (values 4 5))) (list a rest all))",
+        "(1 (2 3) (4 5))",
+    );
+    evals_to(
+        "(let*-values (((a b) (values 1 2)) ((c) (values (+ a b)))) c)",
+        "3",
+    );
+    evals_to(
+        "(receive (a . rest) (values 1 2 3) (list a rest))",
+        "(1 (2 3))",
+    );
+
+    let env = Env::new();
+    eval_str_with_env(&env, "(define x 10)").unwrap();
+    // let-values evaluates every expression before binding anything
+    assert_eq!(
+        eval_str_with_env(&env, "(let-values (((x) (values 1)) ((y) (values x))) y)"),
+        parse("10")
+    );
+    eval_str_with_env(&env, "(define-values (q r) (floor/ 17 5))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(list q r)"), parse("(3 2)"));
+    eval_str_with_env(
+        &env,
+        "(define (loop n) (if (= n 0) 'done (receive (m) (values (- n 1)) (loop m))))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(loop 10000)"), parse("done"));
+}
+
+#[test]
+fn test_promises() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define count 0)").unwrap();
+    eval_str_with_env(
+        &env,
+        "(define p (delay ((lambda () (set! count (+ count 1)) (* 6 7)))))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "count"), parse("0"));
+    assert_eq!(eval_str_with_env(&env, "(force p)"), parse("42"));
+    assert_eq!(eval_str_with_env(&env, "(force p)"), parse("42"));
+    // the promise's expression is only evaluated once
+    assert_eq!(eval_str_with_env(&env, "count"), parse("1"));
+
+    evals_to("(promise? (delay 1))", "#t");
+    evals_to("(promise? 1)", "#f");
+    evals_to("(force (make-promise 5))", "5");
+    evals_to("(force 5)", "5");
+    evals_to("(force (delay-force (delay (+ 1 2))))", "3");
+    assert_eq!(
+        eval_str("(delay 1)").map(|p| p.to_string()),
+        Ok("#<promise>".to_string())
+    );
+    assert!(matches!(
+        eval_str("(force (delay-force 1))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+
+    // a chain of delay-forces is forced without growing the stack
+    eval_str_with_env(
+        &env,
+        "(define (loop n) (delay-force (if (= n 0) (delay 'done) (loop (- n 1)))))",
+    )
+    .unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(force (loop 10000))"),
+        parse("done")
+    );
+}
+
+#[test]
+fn test_streams() {
+    let env = Env::new();
+    eval_str_with_env(
+        &env,
+        "(define (integers-from n) (stream-cons n (integers-from (+ n 1))))",
+    )
+    .unwrap();
+    eval_str_with_env(&env, "(define nat (integers-from 0))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(stream-car nat)"), parse("0"));
+    assert_eq!(
+        eval_str_with_env(&env, "(stream-car (stream-cdr nat))"),
+        parse("1")
+    );
+    assert_eq!(eval_str_with_env(&env, "(stream-ref nat 10)"), parse("10"));
+    assert_eq!(
+        eval_str_with_env(&env, "(stream->list (stream-take 3 nat))"),
+        parse("(0 1 2)")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(stream->list 4 (stream-map (lambda (x) (* x x)) nat))"
+        ),
+        parse("(0 1 4 9)")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(stream->list 3 (stream-filter (lambda (x) (= (mod x 3) 0)) nat))"
+        ),
+        parse("(0 3 6)")
+    );
+    // filtering skips any number of elements in a row
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(stream-car (stream-filter (lambda (x) (> x 10000)) nat))"
+        ),
+        parse("10001")
+    );
+
+    evals_to("(stream-null? stream-null)", "#t");
+    evals_to("(stream-null? (stream-cons 1 stream-null))", "#f");
+    evals_to("(stream-pair? (stream-cons 1 stream-null))", "#t");
+    evals_to("(stream-pair? '(1))", "#f");
+    evals_to("(stream? (stream-cons 1 stream-null))", "#t");
+    evals_to("(stream? (stream-cdr (stream-cons 1 stream-null)))", "#t");
+    evals_to("(stream? stream-null)", "#t");
+    evals_to("(stream? (make-promise 5))", "#f");
+    evals_to("(stream-pair? (delay (cons 1 2)))", "#f");
+    evals_to("(stream->list (list->stream '(1 2 3)))", "(1 2 3)");
+    evals_to(
+        "(stream->list (stream-take 5 (list->stream '(1 2))))",
+        "(1 2)",
+    );
+    // stream-cons doesn't evaluate its arguments
+    evals_to("(stream-pair? (stream-cons (car '()) (car '())))", "#t");
+    assert!(matches!(
+        eval_str("(stream-car stream-null)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_parameters() {
+    let env = Env::new();
+    eval_str_with_env(&env, "(define radix (make-parameter 10))").unwrap();
+    eval_str_with_env(&env, "(define (get-radix) (radix))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(radix)"), parse("10"));
+    assert_eq!(
+        eval_str_with_env(&env, "(parameterize ((radix 2)) (get-radix))"),
+        parse("2")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(parameterize ((radix 2)) (parameterize ((radix 8)) (list (radix))))"
+        ),
+        parse("(8)")
+    );
+    assert_eq!(eval_str_with_env(&env, "(radix)"), parse("10"));
+
+    // the old value is restored when the body raises an error
+    assert!(eval_str_with_env(&env, "(parameterize ((radix 16)) (car '()))").is_err());
+    assert_eq!(eval_str_with_env(&env, "(radix)"), parse("10"));
+
+    // the converter is applied to the initial value and to parameterized values
+    eval_str_with_env(
+        &env,
+        "(define width (make-parameter 1 (lambda (x) (* x 10))))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(width)"), parse("10"));
+    assert_eq!(
+        eval_str_with_env(&env, "(parameterize ((width 5)) (width))"),
+        parse("50")
+    );
+    assert_eq!(eval_str_with_env(&env, "(width)"), parse("10"));
+
+    evals_to("(procedure? (make-parameter 1))", "#t");
+    assert!(matches!(
+        eval_str("(parameterize ((car 1)) 2)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str("((make-parameter 1) 2)"),
+        Err(LispErr::NumArgs(0, _))
+    ));
+
+    // binding a parameter twice in one form still restores its original value
+    eval_str_with_env(&env, "(define p (make-parameter 1))").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(parameterize ((p 2) (p 3)) (p))"),
+        parse("3")
+    );
+    assert_eq!(eval_str_with_env(&env, "(p)"), parse("1"));
+
+    // definitions in the body stay inside it
+    assert_eq!(
+        eval_str_with_env(&env, "(parameterize ((p 5)) (define zz 9) zz)"),
+        parse("9")
+    );
+    assert!(matches!(
+        eval_str_with_env(&env, "zz"),
+        Err(LispErr::UnboundVar(_, _))
+    ));
+}
+
+#[test]
+fn test_case_lambda() {
+    let env = Env::new();
+    eval_str_with_env(
+        &env,
+        "(define area (case-lambda ((r) (* 3 r r)) ((w h) (* w h)) ((w h . more) 'too-many)))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(area 2)"), parse("12"));
+    assert_eq!(eval_str_with_env(&env, "(area 2 5)"), parse("10"));
+    assert_eq!(eval_str_with_env(&env, "(area 1 2 3)"), parse("too-many"));
+    assert_eq!(eval_str_with_env(&env, "(apply area '(3 4))"), parse("12"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(area)"),
+        Err(LispErr::Default(_))
+    ));
+    evals_to("(procedure? (case-lambda ((x) x)))", "#t");
+    assert_eq!(
+        eval_str("(case-lambda ((x) x) ((x . y) y))").map(|f| f.to_string()),
+        Ok("(case-lambda ((x) x) ((x . y) y))".to_string())
+    );
+
+    // clauses are called in tail position
+    eval_str_with_env(
+        &env,
+        "(define count (case-lambda ((n) (count n 0)) ((n acc) (if (= n 0) acc (count (- n 1) (+ acc 1))))))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(count 10000)"), parse("10000"));
+}
+
+#[test]
+fn test_optional_and_keyword_args() {
+    let env = Env::new();
+    eval_str_with_env(
+        &env,
+        "(define* (f a #!optional (b 2) (c (+ a b))) (list a b c))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(f 1)"), parse("(1 2 3)"));
+    assert_eq!(eval_str_with_env(&env, "(f 1 5)"), parse("(1 5 6)"));
+    assert_eq!(eval_str_with_env(&env, "(f 1 5 0)"), parse("(1 5 0)"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(f 1 2 3 4)"),
+        Err(LispErr::NumArgs(1, _))
+    ));
+    assert!(matches!(
+        eval_str_with_env(&env, "(f)"),
+        Err(LispErr::NumArgs(1, _))
+    ));
+
+    eval_str_with_env(
+        &env,
+        "(define* (g a #!optional b #!key (width 10) height) (list a b width height))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(g 1)"), parse("(1 #f 10 #f)"));
+    assert_eq!(
+        eval_str_with_env(&env, "(g 1 #:height 3)"),
+        parse("(1 #f 10 3)")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(g 1 2 #:height 3 #:width 4 #:width 5)"),
+        parse("(1 2 4 3)")
+    );
+    assert!(matches!(
+        eval_str_with_env(&env, "(g 1 2 #:depth 3)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str_with_env(&env, "(g 1 2 3)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+
+    evals_to(
+        "((lambda* (a #!key (b 1) #!rest r) (list a b r)) 0 #:b 2)",
+        "(0 2 (#:b 2))",
+    );
+    evals_to("((lambda* (#!optional (x 'none)) x))", "none");
+    assert_eq!(
+        eval_str("(lambda* (a #!optional (b 1) #!rest r) a)").map(|f| f.to_string()),
+        Ok("(lambda (a #!optional (b 1) #!rest r) a)".to_string())
+    );
+    assert!(matches!(
+        eval_str("(lambda* (a #!optional (1 2)) a)"),
+        Err(LispErr::BadSpecialForm(_, _))
+    ));
+
+    evals_to("#:key", "#:key");
+    evals_to("(keyword? #:key)", "#t");
+    evals_to("(keyword? 'key)", "#f");
+    evals_to("(keyword->symbol #:key)", "key");
+    evals_to("(symbol->keyword 'key)", "#:key");
+}
+
+#[test]
+fn test_unspecified_and_eof() {
+    let env = Env::new();
+    assert_eq!(eval_str_with_env(&env, "(define x 1)"), Ok(Unspecified));
+    assert_eq!(eval_str_with_env(&env, "(define (f) x)"), Ok(Unspecified));
+    assert_eq!(eval_str_with_env(&env, "(set! x 2)"), Ok(Unspecified));
+    assert_eq!(
+        eval_str_with_env(&env, "(define-values (a b) (values 1 2))"),
+        Ok(Unspecified)
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(define-record-type point (make-point x) point? (x point-x set-point-x!))"
+        ),
+        Ok(Unspecified)
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(set-point-x! (make-point 1) 2)"),
+        Ok(Unspecified)
+    );
+    assert_eq!(eval_str("(set-car! (list 1) 2)"), Ok(Unspecified));
+    assert_eq!(eval_str("(vector-set! (vector 1) 0 2)"), Ok(Unspecified));
+    assert_eq!(
+        eval_str("(hash-table-set! (make-hash-table) 1 2)"),
+        Ok(Unspecified)
+    );
+    assert_eq!(eval_str("(for-each car '((1)))"), Ok(Unspecified));
+    assert_eq!(eval_str("(display \"\")"), Ok(Unspecified));
+    assert_eq!(
+        eval_str_with_env(&env, "(list (set! x 3))").map(|l| l.to_string()),
+        Ok("(#<unspecified>)".to_string())
+    );
+
+    assert_eq!(eval_str("(eof-object)"), Ok(Eof));
+    evals_to("(eof-object? (eof-object))", "#t");
+    evals_to("(eof-object? '())", "#f");
+    evals_to("(eq? (eof-object) (eof-object))", "#t");
+    assert_eq!(
+        eval_str("(eof-object)").map(|e| e.to_string()),
+        Ok("#<eof>".to_string())
+    );
+}
+
+fn input_port(text: &[u8], binary: bool) -> LispVal {
+    let reader = std::io::Cursor::new(text.to_vec());
+    ports::new_port("test", binary, InputPort(reader))
+}
+
+#[test]
+fn test_reading_ports() {
+    let env = Env::new();
+    env.define(
+        "in".to_string(),
+        input_port("aλ\r\nsecond line\nrest".as_bytes(), false),
+    );
+    assert_eq!(eval_str_with_env(&env, "(peek-char in)"), Ok(Char('a')));
+    assert_eq!(eval_str_with_env(&env, "(read-char in)"), Ok(Char('a')));
+    assert_eq!(eval_str_with_env(&env, "(read-char in)"), Ok(Char('λ')));
+    assert_eq!(eval_str_with_env(&env, "(read-line in)"), parse("\"\""));
+    assert_eq!(
+        eval_str_with_env(&env, "(read-line in)"),
+        parse("\"second line\"")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(read-string 2 in)"),
+        parse("\"re\"")
+    );
+    assert_eq!(eval_str_with_env(&env, "(char-ready? in)"), parse("#t"));
+    assert_eq!(
+        eval_str_with_env(&env, "(read-string 5 in)"),
+        parse("\"st\"")
+    );
+    assert_eq!(eval_str_with_env(&env, "(read-char in)"), Ok(Eof));
+    assert_eq!(eval_str_with_env(&env, "(peek-char in)"), Ok(Eof));
+    assert_eq!(eval_str_with_env(&env, "(read-line in)"), Ok(Eof));
+    assert_eq!(eval_str_with_env(&env, "(read-string 1 in)"), Ok(Eof));
+
+    env.define("bad".to_string(), input_port(&[0xff, b'x'], false));
+    assert_eq!(
+        eval_str_with_env(&env, "(read-char bad)"),
+        Ok(Char('\u{fffd}'))
+    );
+    assert_eq!(eval_str_with_env(&env, "(read-char bad)"), Ok(Char('x')));
+
+    env.define("bytes".to_string(), input_port(&[1, 2], true));
+    assert_eq!(eval_str_with_env(&env, "(peek-u8 bytes)"), parse("1"));
+    assert_eq!(eval_str_with_env(&env, "(read-u8 bytes)"), parse("1"));
+    assert_eq!(eval_str_with_env(&env, "(read-u8 bytes)"), parse("2"));
+    assert_eq!(eval_str_with_env(&env, "(read-u8 bytes)"), Ok(Eof));
+    assert!(matches!(
+        eval_str_with_env(&env, "(read-char bytes)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+
+    assert_eq!(
+        eval_str_with_env(&env, "(input-port-open? in)"),
+        parse("#t")
+    );
+    eval_str_with_env(&env, "(close-input-port in)").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(input-port-open? in)"),
+        parse("#f")
+    );
+    assert!(matches!(
+        eval_str_with_env(&env, "(read-char in)"),
+        Err(LispErr::Default(_))
+    ));
+}
+
+#[test]
+fn test_writing_ports() {
+    let env = Env::new();
+    let output = OutputBuffer::new();
+    let port = ports::new_port("test", false, output.clone());
+    env.define("out".to_string(), port);
+    eval_str_with_env(&env, r#"(display "a \"b\"" out)"#).unwrap();
+    eval_str_with_env(&env, r#"(write "a \"b\"" out)"#).unwrap();
+    eval_str_with_env(&env, "(newline out)").unwrap();
+    eval_str_with_env(&env, r"(write-char #\λ out)").unwrap();
+    eval_str_with_env(&env, r#"(write-string "abcdef" out 1 3)"#).unwrap();
+    eval_str_with_env(&env, r"(write '(1 #\x) out)").unwrap();
+    // the current output port is a parameter, so it can be redirected
+    eval_str_with_env(
+        &env,
+        r#"(parameterize ((current-output-port out)) (display "!") (flush-output-port))"#,
+    )
+    .unwrap();
+    assert_eq!(output.text(), "a \"b\"\"a \\\"b\\\"\"\nλbc(1 #\\x)!");
+
+    assert_eq!(eval_str_with_env(&env, "(output-port? out)"), parse("#t"));
+    assert_eq!(eval_str_with_env(&env, "(input-port? out)"), parse("#f"));
+    assert_eq!(eval_str_with_env(&env, "(textual-port? out)"), parse("#t"));
+    assert_eq!(eval_str_with_env(&env, "(binary-port? out)"), parse("#f"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(write-u8 1 out)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str_with_env(&env, "(read-char out)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    eval_str_with_env(&env, "(close-port out)").unwrap();
+    assert!(matches!(
+        eval_str_with_env(&env, "(display 1 out)"),
+        Err(LispErr::Default(_))
+    ));
+
+    evals_to("(port? (current-output-port))", "#t");
+    evals_to("(input-port? (current-input-port))", "#t");
+    evals_to("(output-port? (current-error-port))", "#t");
+    evals_to("(port? 1)", "#f");
+}
+
+#[test]
+fn test_string_ports() {
+    let env = Env::new();
+    eval_str_with_env(&env, r#"(define in (open-input-string "one\ntwo"))"#).unwrap();
+    assert_eq!(eval_str_with_env(&env, "(read-line in)"), parse("\"one\""));
+    assert_eq!(eval_str_with_env(&env, "(read-char in)"), Ok(Char('t')));
+    assert_eq!(eval_str_with_env(&env, "(read-line in)"), parse("\"wo\""));
+    assert_eq!(eval_str_with_env(&env, "(read-line in)"), Ok(Eof));
+
+    eval_str_with_env(&env, "(define out (open-output-string))").unwrap();
+    eval_str_with_env(&env, "(write 'a out)").unwrap();
+    eval_str_with_env(&env, r#"(write-string " λ" out)"#).unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(get-output-string out)"),
+        parse("\"a λ\"")
+    );
+    eval_str_with_env(&env, "(display 1 out)").unwrap();
+    assert_eq!(
+        eval_str_with_env(&env, "(get-output-string out)"),
+        parse("\"a λ1\"")
+    );
+
+    evals_to(
+        "(call-with-output-string (lambda (port) (display \"x\" port) (write \"y\" port)))",
+        r#""x\"y\"""#,
+    );
+    evals_to(
+        "(with-output-to-string (lambda () (display '(1 2)) (newline)))",
+        "\"(1 2)\n\"",
+    );
+    evals_to(
+        r#"(with-input-from-string "abc" (lambda () (read-char) (read-string 5)))"#,
+        "\"bc\"",
+    );
+
+    // the current output port is restored even if the thunk fails
+    assert!(eval_str_with_env(&env, "(with-output-to-string (lambda () (car '())))").is_err());
+    assert_eq!(
+        eval_str_with_env(&env, "(eq? (current-output-port) out)"),
+        parse("#f")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(parameterize ((current-output-port out)) (eq? (current-output-port) out))"
+        ),
+        parse("#t")
+    );
+
+    assert!(matches!(
+        eval_str("(get-output-string (current-output-port))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(get-output-string (open-input-string \"\"))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_guard() {
+    evals_to("(guard (e (#t 'caught)) (car '()))", "caught");
+    evals_to("(guard (e (#t 'caught)) 'fine)", "fine");
+    evals_to("(guard (e ((error-object? e))) (car '()))", "#t");
+    evals_to(
+        "(guard (e ((file-error? e) 'file) (else (error-object-message e))) (car '()))",
+        "\"Expected an cons cell\"",
+    );
+    evals_to(
+        "(guard (e (else (error-object-irritants e))) (vector-ref (vector) 3))",
+        "(3)",
+    );
+    evals_to(
+        "(guard (e ((error-object? e) 'outer)) (guard (e2 (#f 'inner)) (undefined-var)))",
+        "outer",
+    );
+    assert!(matches!(
+        eval_str("(guard (e ((file-error? e) 'file)) (car '()))"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    evals_to("(error-object? 'e)", "#f");
+    evals_to(
+        "(guard (e ((error-object-message e) => string-length)) (car 1))",
+        "21",
+    );
+    evals_to(
+        "(guard (e ((error-object? e) => (lambda (x) (list x)))) (car 1))",
+        "(#t)",
+    );
+    assert!(matches!(
+        eval_str("(guard (e (#t =>)) (car 1))"),
+        Err(LispErr::BadSpecialForm(_, _))
+    ));
+
+    // definitions in the body stay inside the guard, whether or not it raises
+    let env = Env::new();
+    assert_eq!(
+        eval_str_with_env(&env, "(guard (e (#t 1)) (define zz 5) (car '()))"),
+        parse("1")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(guard (e (#t 1)) (define zz 5) zz)"),
+        parse("5")
+    );
+    assert!(matches!(
+        eval_str_with_env(&env, "zz"),
+        Err(LispErr::UnboundVar(_, _))
+    ));
+}
+
+#[test]
+fn test_files() {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("risp-test-{}.txt", std::process::id()));
+    let path = format!("{:?}", path.to_str().unwrap());
+    let env = Env::new();
+    eval_str_with_env(&env, &format!("(define path {})", path)).unwrap();
+
+    eval_str_with_env(
+        &env,
+        r#"(call-with-output-file path (lambda (port) (display "first" port) (newline port)))"#,
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(file-exists? path)"), parse("#t"));
+    assert_eq!(
+        eval_str_with_env(&env, "(call-with-input-file path read-line)"),
+        parse("\"first\"")
+    );
+
+    eval_str_with_env(
+        &env,
+        r#"(with-output-to-file path (lambda () (write "second")))"#,
+    )
+    .unwrap();
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(with-input-from-file path (lambda () (read-string 100)))"
+        ),
+        parse(r#""\"second\"""#)
+    );
+
+    eval_str_with_env(&env, "(define out (open-binary-output-file path))").unwrap();
+    eval_str_with_env(&env, "(write-u8 255 out)").unwrap();
+    eval_str_with_env(&env, "(close-port out)").unwrap();
+    eval_str_with_env(&env, "(define in (open-binary-input-file path))").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(read-u8 in)"), parse("255"));
+    assert_eq!(eval_str_with_env(&env, "(read-u8 in)"), Ok(Eof));
+    eval_str_with_env(&env, "(close-port in)").unwrap();
+
+    eval_str_with_env(&env, "(delete-file path)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(file-exists? path)"), parse("#f"));
+    assert!(matches!(
+        eval_str_with_env(&env, "(open-input-file path)"),
+        Err(LispErr::FileError(_, _))
+    ));
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(guard (e ((file-error? e) 'missing)) (delete-file path))"
+        ),
+        parse("missing")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(guard (e ((file-error? e) (equal? (error-object-irritants e) (list path)))) (open-input-file path))"
+        ),
+        parse("#t")
+    );
+
+    // failing to write to a file once it's open is a file error too
+    if std::path::Path::new("/dev/full").exists() {
+        evals_to(
+            r#"(guard (e ((file-error? e) (error-object-irritants e))) (with-output-to-file "/dev/full" (lambda () (display "x"))))"#,
+            r#"("/dev/full")"#,
+        );
+    }
+}
+
+#[test]
+fn test_read() {
+    let env = Env::new();
+    let data = input_port(
+        br#"  (define (f x) ; a comment
+    (+ x 1))
+  'quoted #(1 #\( "a ) \" b") #u8(1 2)
+  sym "str" #\space 42 (1 . 2);trailing"#,
+        false,
+    );
+    env.define("in".to_string(), data);
+    let expected = [
+        "(define (f x) (+ x 1))",
+        "(quote quoted)",
+        r#"#(1 #\( "a ) \" b")"#,
+        "#u8(1 2)",
+        "sym",
+        "\"str\"",
+        r"#\space",
+        "42",
+        "(1 . 2)",
+    ];
+    for datum in expected.iter() {
+        assert_eq!(eval_str_with_env(&env, "(read in)"), parse(datum));
+    }
+    assert_eq!(eval_str_with_env(&env, "(read in)"), Ok(Eof));
+    assert_eq!(eval_str_with_env(&env, "(read in)"), Ok(Eof));
+
+    // what's read is data, which can be taken apart like any other list
+    evals_to(
+        r#"(with-input-from-string "(a b) c" (lambda () (car (cdr (read)))))"#,
+        "b",
+    );
+    evals_to(
+        r#"(guard (e ((read-error? e) 'bad)) (read (open-input-string "(1 2")))"#,
+        "bad",
+    );
+    evals_to(
+        r#"(guard (e ((read-error? e) 'bad)) (read (open-input-string ")")))"#,
+        "bad",
+    );
+
+    // block comments nest, and #; comments out the next datum
+    evals_to(r##"(read (open-input-string "#|c|# 2"))"##, "2");
+    evals_to(r##"(read (open-input-string "#| a #| b |# c |#2"))"##, "2");
+    evals_to(r##"(read (open-input-string "#;1 2"))"##, "2");
+    evals_to(r##"(read (open-input-string "#;(a (b)) #;c 2"))"##, "2");
+    evals_to(
+        r##"(read (open-input-string "(1 #|two|# #;(3) 4 #;5)"))"##,
+        "(1 4)",
+    );
+    evals_to(r##"(read (open-input-string "(a#|b|#c)"))"##, "(a c)");
+    evals_to(r##"(read (open-input-string "\"#|\""))"##, r##""#|""##);
+}
+
+#[test]
+fn test_eval_and_environments() {
+    evals_to("(eval '(+ 1 2) (scheme-report-environment 5))", "3");
+    evals_to(
+        "(eval (list 'car ''(a b)) (environment '(scheme base)))",
+        "a",
+    );
+    evals_to("(eval '(if #t 1 2) (null-environment 5))", "1");
+    assert!(matches!(
+        eval_str("(eval '(car '(1)) (null-environment 5))"),
+        Err(LispErr::UnboundVar(_, _))
+    ));
+
+    // definitions made by eval stay in the environment, apart from everything else
+    let env = Env::new();
+    eval_str_with_env(&env, "(define e (scheme-report-environment 5))").unwrap();
+    eval_str_with_env(&env, "(eval '(define x 10) e)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(eval 'x e)"), parse("10"));
+    assert!(matches!(
+        eval_str_with_env(&env, "x"),
+        Err(LispErr::UnboundVar(_, _))
+    ));
+
+    // the interaction environment is the top level, even from inside a procedure
+    eval_str_with_env(&env, "(define y 1)").unwrap();
+    eval_str_with_env(
+        &env,
+        "(define (f y) (eval '(define z (+ y 1)) (interaction-environment)))",
+    )
+    .unwrap();
+    eval_str_with_env(&env, "(f 100)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "z"), parse("2"));
+
+    // interaction-environment is a procedure like any other
+    assert_eq!(
+        eval_str_with_env(&env, "(procedure? interaction-environment)"),
+        parse("#t")
+    );
+    eval_str_with_env(&env, "(define ie interaction-environment)").unwrap();
+    assert_eq!(eval_str_with_env(&env, "(eval 'z (ie))"), parse("2"));
+    assert_eq!(
+        eval_str_with_env(&env, "(eval 'y (apply interaction-environment '()))"),
+        parse("1")
+    );
+    assert_eq!(
+        eval_str_with_env(&env, "(eq? (interaction-environment) (ie))"),
+        parse("#t")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(eval 'z (car (call-with-values interaction-environment list)))"
+        ),
+        parse("2")
+    );
+    assert_eq!(
+        eval_str_with_env(
+            &env,
+            "(eval 'y (car (map (lambda (f) (f)) (list interaction-environment))))"
+        ),
+        parse("1")
+    );
+
+    // a meta-circular loop through eval runs in constant space
+    eval_str_with_env(
+        &env,
+        "(define (count n) (if (= n 0) 'done (eval (list 'count (- n 1)) (interaction-environment))))",
+    )
+    .unwrap();
+    assert_eq!(eval_str_with_env(&env, "(count 100000)"), parse("done"));
+    evals_to("(apply eval (list '(* 2 3) (environment)))", "6");
+
+    assert!(matches!(
+        eval_str("(environment '(scheme unknown))"),
+        Err(LispErr::Default(_))
+    ));
+    assert!(matches!(
+        eval_str("(scheme-report-environment 7)"),
+        Err(LispErr::OutOfRange(_, _))
+    ));
+    assert!(matches!(
+        eval_str("(eval 1 2)"),
+        Err(LispErr::TypeMismatch(_, _))
+    ));
+    assert_eq!(
+        eval_str("(environment)").map(|e| e.to_string()),
+        Ok("#<environment>".to_string())
+    );
 }
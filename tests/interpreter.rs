@@ -0,0 +1,114 @@
+use risp::ast::LispVal::*;
+use risp::interpreter::Interpreter;
+use risp::ports::{self, InputOutputPort, InputPort, OutputBuffer, OutputPort, Port};
+use std::io::Cursor;
+
+#[test]
+fn test_current_ports() {
+    let output = OutputBuffer::new();
+    let error = OutputBuffer::new();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_input_port(InputPort(Cursor::new("first\nsecond")));
+    interpreter.set_output_port(output.clone());
+    interpreter.set_error_port(error.clone());
+
+    interpreter
+        .eval_str("(define (echo) (display (read-line)) (newline))")
+        .unwrap();
+    interpreter.eval_str("(echo)").unwrap();
+    interpreter
+        .eval_str(r#"(display "oops" (current-error-port))"#)
+        .unwrap();
+    interpreter.eval_str("(echo)").unwrap();
+    assert_eq!(output.text(), "first\nsecond\n");
+    assert_eq!(error.text(), "oops");
+    assert_eq!(interpreter.eval_str("(read-line)"), Ok(Eof));
+
+    // output written before an error is still there
+    assert!(interpreter
+        .eval_str(r#"((lambda () (display "partial") (car '())))"#)
+        .is_err());
+    assert_eq!(output.text(), "first\nsecond\npartial");
+
+    // the ports are only current while the interpreter is evaluating
+    let other = OutputBuffer::new();
+    let mut second = Interpreter::new();
+    second.set_output_port(other.clone());
+    second.eval_str("(display 1)").unwrap();
+    assert_eq!(other.text(), "1");
+    assert_eq!(output.text(), "first\nsecond\npartial");
+}
+
+// A port that hands out its input a byte at a time and records what's written, like a socket
+struct Trickle {
+    input: Vec<u8>,
+    output: OutputBuffer,
+}
+
+impl std::io::Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.input.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.input.remove(0);
+        Ok(1)
+    }
+}
+
+impl std::io::Write for Trickle {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_custom_ports() {
+    let interpreter = Interpreter::new();
+    let sent = OutputBuffer::new();
+    let socket = Trickle {
+        input: "λ ok\n".as_bytes().to_vec(),
+        output: sent.clone(),
+    };
+    interpreter.define(
+        "socket",
+        ports::new_port("socket", false, InputOutputPort(socket)),
+    );
+    assert_eq!(interpreter.eval_str("(read-char socket)"), Ok(Char('λ')));
+    assert_eq!(
+        interpreter
+            .eval_str("(read-line socket)")
+            .map(|s| s.to_string()),
+        Ok("\" ok\"".to_string())
+    );
+    interpreter
+        .eval_str(r#"(write-string "reply" socket)"#)
+        .unwrap();
+    assert_eq!(sent.text(), "reply");
+    assert_eq!(interpreter.eval_str("(input-port? socket)"), Ok(Bool(true)));
+    assert_eq!(
+        interpreter.eval_str("(output-port? socket)"),
+        Ok(Bool(true))
+    );
+
+    let bytes = OutputBuffer::new();
+    interpreter.define(
+        "log",
+        ports::new_port("log", true, OutputPort(bytes.clone())),
+    );
+    interpreter.eval_str("(write-u8 255 log)").unwrap();
+    assert_eq!(bytes.contents(), vec![255]);
+
+    // a port type of its own that reads nothing and writes nowhere
+    struct Closed;
+    impl Port for Closed {}
+    interpreter.define("closed", ports::new_port("closed", false, Closed));
+    assert_eq!(interpreter.eval_str("(port? closed)"), Ok(Bool(true)));
+    assert_eq!(
+        interpreter.eval_str("(input-port? closed)"),
+        Ok(Bool(false))
+    );
+}
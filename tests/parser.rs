@@ -1,8 +1,10 @@
-use risp::ast::LispVal::{Atom, Bool, Str};
+use risp::ast::LispVal;
+use risp::ast::LispVal::{Atom, Bool, Char, Keyword, Str};
 use risp::parser::parser_combinator;
 
 use nom::combinator::all_consuming;
 use nom::{error::ErrorKind, Err::Error, IResult};
+use quickcheck::quickcheck;
 
 #[test]
 fn test_parse_true() {
@@ -62,5 +64,94 @@ fn test_parse_expr() {
     parseSuccess("\"1\"");
     parseSuccess("(1 . 2)");
     parseSuccess("\"1\"");
-    parseSuccess("(define (adder x y) (+ x y))")
+    parseSuccess("(define (adder x y) (+ x y))");
+    parseSuccess("(#\\a #\\space #\\null)");
+    parseSuccess("#(1 2 3)");
+    parseSuccess("#()");
+    parseSuccess("(#(a #(b)) c)");
+    parseSuccess("#u8(0 1 255)");
+    parseSuccess("#u8()");
+    parse_to_success("( 1\n\t2 )", "(1 2)");
+    parse_to_success("(\n)", "()");
+    parse_to_success("(1\n.\n2)", "(1 . 2)");
+    assert!(all_consuming(parser_combinator::bytevector)("#u8(256)").is_err());
+    assert!(all_consuming(parser_combinator::bytevector)("#u8(a)").is_err());
+}
+
+#[test]
+fn test_parse_char() {
+    fn parse_to(string: &str, to: char) {
+        let c = all_consuming(parser_combinator::expr)(string);
+        assert_eq!(c, Ok(("", Char(to))))
+    }
+    parse_to("#\\a", 'a');
+    parse_to("#\\A", 'A');
+    parse_to("#\\(", '(');
+    parse_to("#\\ ", ' ');
+    parse_to("#\\x", 'x');
+    parse_to("#\\space", ' ');
+    parse_to("#\\newline", '\n');
+    parse_to("#\\x41", 'A');
+    parse_to("#\\x3bb", 'λ');
+    parse_to("#\\λ", 'λ');
+    assert!(all_consuming(parser_combinator::expr)("#\\nonsense").is_err());
+    assert!(all_consuming(parser_combinator::expr)("#\\xdeadbeef").is_err());
+}
+
+#[test]
+fn test_parse_string_escapes() {
+    fn parse_to(string: &str, to: &str) {
+        let s = all_consuming(parser_combinator::string)(string);
+        assert_eq!(s, Ok(("", Str(to.into()))))
+    }
+    parse_to(r#""plain""#, "plain");
+    parse_to(r#""say \"hi\"""#, "say \"hi\"");
+    parse_to(r#""back\\slash""#, "back\\slash");
+    parse_to(r#""tab\tnewline\nreturn\r""#, "tab\tnewline\nreturn\r");
+    parse_to(r#""\a\b\|""#, "\u{7}\u{8}|");
+    parse_to(r#""\x41;\x3bb;""#, "Aλ");
+    parse_to("\"line \\  \n   continued\"", "line continued");
+    parse_to("\"line \\\r\ncontinued\"", "line continued");
+    assert!(parser_combinator::string(r#""\q""#).is_err());
+    assert!(parser_combinator::string(r#""\x41""#).is_err());
+    assert!(parser_combinator::string(r#""unterminated"#).is_err());
+}
+
+#[test]
+fn test_string_round_trip() {
+    fn round_trips(s: String) -> bool {
+        let written = format!("{}", Str(s.as_str().into()));
+        parser_combinator::scheme(&written) == Ok(Str(s.into()))
+    }
+    quickcheck(round_trips as fn(String) -> bool);
+}
+
+#[test]
+fn test_string_list_round_trip() {
+    fn round_trips(strings: Vec<String>) -> bool {
+        let list = LispVal::list(
+            &strings
+                .into_iter()
+                .map(|s| Str(s.into()))
+                .collect::<Vec<_>>(),
+        );
+        parser_combinator::scheme(&format!("{}", list)) == Ok(list)
+    }
+    quickcheck(round_trips as fn(Vec<String>) -> bool);
+}
+
+#[test]
+fn test_keyword() {
+    assert_eq!(
+        parser_combinator::scheme("#:width"),
+        Ok(Keyword("width".to_string()))
+    );
+    assert_eq!(
+        parser_combinator::scheme("(f #:x 1)").map(|l| l.to_string()),
+        Ok("(f #:x 1)".to_string())
+    );
+    assert_eq!(
+        parser_combinator::scheme("#!optional"),
+        Ok(Atom("#!optional".to_string()))
+    );
 }
@@ -0,0 +1,46 @@
+use quickcheck::quickcheck;
+use risp::strings::LispString;
+
+#[test]
+fn test_char_len() {
+    assert_eq!(LispString::new("").char_len(), 0);
+    assert_eq!(LispString::new("ascii").char_len(), 5);
+    assert_eq!(LispString::new("naïve").char_len(), 5);
+}
+
+#[test]
+fn test_char_at_long_string() {
+    let text: String = "aλ€😀".repeat(50);
+    let s = LispString::new(&text);
+    assert_eq!(s.char_len(), 200);
+    assert_eq!(s.char_at(0), Some('a'));
+    assert_eq!(s.char_at(33), Some('λ'));
+    assert_eq!(s.char_at(199), Some('😀'));
+    assert_eq!(s.char_at(200), None);
+    assert_eq!(s.byte_offset(200), Some(text.len()));
+    assert_eq!(s.byte_offset(201), None);
+}
+
+#[test]
+fn test_char_at_matches_chars() {
+    fn matches(text: String) -> bool {
+        let s = LispString::new(&text);
+        s.char_len() == text.chars().count()
+            && text
+                .chars()
+                .enumerate()
+                .all(|(i, c)| s.char_at(i) == Some(c))
+    }
+    quickcheck(matches as fn(String) -> bool);
+}
+
+#[test]
+fn test_byte_offset_matches_char_indices() {
+    fn matches(text: String) -> bool {
+        let s = LispString::new(&text);
+        text.char_indices()
+            .enumerate()
+            .all(|(i, (offset, _))| s.byte_offset(i) == Some(offset))
+    }
+    quickcheck(matches as fn(String) -> bool);
+}